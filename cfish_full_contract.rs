@@ -1,8 +1,19 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
 use anchor_spl::{
-    token::{Mint, Token, TokenAccount, Transfer, transfer},
-    associated_token::AssociatedToken,
+    token::{
+        Mint, Token, TokenAccount, Transfer, transfer, MintTo, mint_to, SetAuthority, set_authority,
+        CloseAccount, close_account, Approve, approve, Revoke, revoke,
+        spl_token::instruction::AuthorityType,
+    },
+    associated_token::{AssociatedToken, Create, create_idempotent, get_associated_token_address},
+    metadata::{
+        Metadata, CreateMetadataAccountsV3, create_metadata_accounts_v3, CreateMasterEditionV3,
+        create_master_edition_v3,
+        mpl_token_metadata::types::{Collection, Creator, DataV2},
+    },
 };
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
@@ -17,26 +28,285 @@ pub mod cfish_contract {
         name: String,
         symbol: String,
         uri: String,
+        seller_fee_basis_points: u16,
+        creators: Vec<CreatorShare>,
+        collection: Option<Pubkey>, // Mint of the collection NFT, verified later by its creator
     ) -> Result<()> {
+        validate_creators(&creators, seller_fee_basis_points)?;
+
         // Simplified NFT minting without Metaplex dependency
-        // In production, this would integrate with Metaplex Token Metadata
-        
+        // Use mint_nft_metaplex for NFTs that wallets and other marketplaces can see
+
+        // Mint exactly one token into the creator's associated token account
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.creator_token_account.to_account_info(),
+            authority: ctx.accounts.mint_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        mint_to(cpi_ctx, 1)?;
+
+        // Revoke the mint authority so the supply is fixed at one
+        let cpi_accounts = SetAuthority {
+            current_authority: ctx.accounts.mint_authority.to_account_info(),
+            account_or_mint: ctx.accounts.mint.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        set_authority(cpi_ctx, AuthorityType::MintTokens, None)?;
+
         // Store NFT metadata in our custom account
         ctx.accounts.nft_metadata.name = name;
         ctx.accounts.nft_metadata.symbol = symbol;
         ctx.accounts.nft_metadata.uri = uri;
         ctx.accounts.nft_metadata.mint = ctx.accounts.mint.key();
         ctx.accounts.nft_metadata.creator = ctx.accounts.mint_authority.key();
-        
+        ctx.accounts.nft_metadata.seller_fee_basis_points = seller_fee_basis_points;
+        ctx.accounts.nft_metadata.creators = creators;
+        ctx.accounts.nft_metadata.collection = collection;
+        ctx.accounts.nft_metadata.collection_verified = false;
+
         msg!("NFT minted successfully: {}", ctx.accounts.nft_metadata.key());
         Ok(())
     }
 
+    // NFT Core Program: mint_nft_metaplex instruction
+    // Creates Metaplex metadata and master edition accounts so wallets and other
+    // marketplaces can see the NFT, and mirrors it into our NftMetadata index.
+    pub fn mint_nft_metaplex(
+        ctx: Context<MintNftMetaplex>,
+        name: String,
+        symbol: String,
+        uri: String,
+        seller_fee_basis_points: u16,
+        creators: Vec<CreatorShare>,
+        collection: Option<Pubkey>,
+    ) -> Result<()> {
+        validate_creators(&creators, seller_fee_basis_points)?;
+
+        // Mint exactly one token into the creator's associated token account
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.creator_token_account.to_account_info(),
+            authority: ctx.accounts.mint_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        mint_to(cpi_ctx, 1)?;
+
+        // Create the Metaplex metadata account
+        let mint_authority_key = ctx.accounts.mint_authority.key();
+        let data = DataV2 {
+            name: name.clone(),
+            symbol: symbol.clone(),
+            uri: uri.clone(),
+            seller_fee_basis_points,
+            creators: (!creators.is_empty()).then(|| {
+                creators
+                    .iter()
+                    .map(|creator| Creator {
+                        address: creator.address,
+                        // Only the signing mint authority can be verified in this instruction
+                        verified: creator.address == mint_authority_key,
+                        share: creator.share,
+                    })
+                    .collect()
+            }),
+            collection: collection.map(|key| Collection { verified: false, key }),
+            uses: None,
+        };
+        let cpi_accounts = CreateMetadataAccountsV3 {
+            metadata: ctx.accounts.metadata.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            mint_authority: ctx.accounts.mint_authority.to_account_info(),
+            payer: ctx.accounts.payer.to_account_info(),
+            update_authority: ctx.accounts.mint_authority.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_metadata_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        create_metadata_accounts_v3(cpi_ctx, data, true, true, None)?;
+
+        // Create the master edition with a max supply of zero. This moves the mint
+        // and freeze authorities to the edition account, fixing the supply at one.
+        let cpi_accounts = CreateMasterEditionV3 {
+            edition: ctx.accounts.master_edition.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            update_authority: ctx.accounts.mint_authority.to_account_info(),
+            mint_authority: ctx.accounts.mint_authority.to_account_info(),
+            payer: ctx.accounts.payer.to_account_info(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_metadata_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        create_master_edition_v3(cpi_ctx, Some(0))?;
+
+        // Mirror the NFT into our custom metadata index
+        ctx.accounts.nft_metadata.name = name;
+        ctx.accounts.nft_metadata.symbol = symbol;
+        ctx.accounts.nft_metadata.uri = uri;
+        ctx.accounts.nft_metadata.mint = ctx.accounts.mint.key();
+        ctx.accounts.nft_metadata.creator = ctx.accounts.mint_authority.key();
+        ctx.accounts.nft_metadata.seller_fee_basis_points = seller_fee_basis_points;
+        ctx.accounts.nft_metadata.creators = creators;
+        ctx.accounts.nft_metadata.collection = collection;
+        ctx.accounts.nft_metadata.collection_verified = false;
+
+        msg!("Metaplex NFT minted successfully: {}", ctx.accounts.metadata.key());
+        Ok(())
+    }
+
+    // NFT Core Program: create_master_edition instruction
+    // Turns an existing NFT into a master edition that can be printed up to max_supply times
+    pub fn create_master_edition(
+        ctx: Context<CreateMasterEdition>,
+        max_supply: u64,
+    ) -> Result<()> {
+        require!(max_supply > 0, CustomError::InvalidMaxSupply);
+
+        ctx.accounts.master_edition.mint = ctx.accounts.mint.key();
+        ctx.accounts.master_edition.max_supply = max_supply;
+        ctx.accounts.master_edition.supply = 0;
+        ctx.accounts.master_edition.bump = ctx.bumps.master_edition;
+
+        msg!("Master edition created with max supply: {}", max_supply);
+        Ok(())
+    }
+
+    // NFT Core Program: print_edition instruction
+    // Mints the next numbered print of a master edition into the creator's wallet
+    pub fn print_edition(
+        ctx: Context<PrintEdition>,
+    ) -> Result<()> {
+        let master_edition = &mut ctx.accounts.master_edition;
+        require!(master_edition.supply < master_edition.max_supply, CustomError::EditionSupplyExhausted);
+        master_edition.supply = master_edition.supply.checked_add(1).unwrap();
+        let edition_number = master_edition.supply;
+
+        // Mint exactly one token into the creator's associated token account
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.edition_mint.to_account_info(),
+            to: ctx.accounts.creator_token_account.to_account_info(),
+            authority: ctx.accounts.creator.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        mint_to(cpi_ctx, 1)?;
+
+        // Revoke the mint authority so the supply is fixed at one
+        let cpi_accounts = SetAuthority {
+            current_authority: ctx.accounts.creator.to_account_info(),
+            account_or_mint: ctx.accounts.edition_mint.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        set_authority(cpi_ctx, AuthorityType::MintTokens, None)?;
+
+        // Copy the master metadata into the print's own record
+        let master_metadata = &ctx.accounts.master_nft_metadata;
+        ctx.accounts.nft_metadata.name = master_metadata.name.clone();
+        ctx.accounts.nft_metadata.symbol = master_metadata.symbol.clone();
+        ctx.accounts.nft_metadata.uri = master_metadata.uri.clone();
+        ctx.accounts.nft_metadata.mint = ctx.accounts.edition_mint.key();
+        ctx.accounts.nft_metadata.creator = master_metadata.creator;
+        ctx.accounts.nft_metadata.seller_fee_basis_points = master_metadata.seller_fee_basis_points;
+        ctx.accounts.nft_metadata.creators = master_metadata.creators.clone();
+        ctx.accounts.nft_metadata.collection = master_metadata.collection;
+        ctx.accounts.nft_metadata.collection_verified = master_metadata.collection_verified;
+
+        // Link the print back to its master
+        ctx.accounts.edition.parent = ctx.accounts.master_mint.key();
+        ctx.accounts.edition.edition = edition_number;
+
+        msg!("Printed edition {} of {}", edition_number, ctx.accounts.master_edition.max_supply);
+        Ok(())
+    }
+
+    // NFT Core Program: verify_collection instruction
+    // The creator of the collection NFT confirms that an NFT belongs to the collection
+    pub fn verify_collection(
+        ctx: Context<VerifyCollection>,
+    ) -> Result<()> {
+        ctx.accounts.nft_metadata.collection_verified = true;
+
+        msg!("NFT {} verified in collection {}", ctx.accounts.nft_metadata.mint, ctx.accounts.collection_mint.key());
+        Ok(())
+    }
+
+    // Marketplace Program: initialize_marketplace_config instruction
+    pub fn initialize_marketplace_config(
+        ctx: Context<InitializeMarketplaceConfig>,
+        treasury: Pubkey,
+        cfish_mint: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.marketplace_config.authority = ctx.accounts.authority.key();
+        ctx.accounts.marketplace_config.fee_basis_points = PLATFORM_FEE_BASIS_POINTS;
+        ctx.accounts.marketplace_config.treasury = treasury;
+        ctx.accounts.marketplace_config.cfish_mint = cfish_mint;
+        ctx.accounts.marketplace_config.referral_share_bps = REFERRAL_SHARE_BASIS_POINTS;
+        ctx.accounts.marketplace_config.cfish_per_sol = 0;
+        ctx.accounts.marketplace_config.bump = ctx.bumps.marketplace_config;
+
+        msg!("Marketplace config initialized with fee: {} bps", PLATFORM_FEE_BASIS_POINTS);
+        Ok(())
+    }
+
+    // Marketplace Program: update_marketplace_config instruction
+    pub fn update_marketplace_config(
+        ctx: Context<UpdateMarketplaceConfig>,
+        fee_basis_points: u16,
+        treasury: Pubkey,
+    ) -> Result<()> {
+        require!(fee_basis_points <= MAX_PLATFORM_FEE_BASIS_POINTS, CustomError::InvalidFeeBasisPoints);
+
+        ctx.accounts.marketplace_config.fee_basis_points = fee_basis_points;
+        ctx.accounts.marketplace_config.treasury = treasury;
+
+        msg!("Marketplace config updated with fee: {} bps", fee_basis_points);
+        Ok(())
+    }
+
+    // Marketplace Program: update_referral_config instruction
+    // Sets the referrer share of the platform fee and the CFISH paid per SOL of that share.
+    // Creates the CFISH vault that referral rewards are claimed from. The vault is funded
+    // by plain CFISH transfers to its address.
+    pub fn update_referral_config(
+        ctx: Context<UpdateReferralConfig>,
+        referral_share_bps: u16,
+        cfish_per_sol: u64, // CFISH base units per SOL of commission
+    ) -> Result<()> {
+        require!(
+            (MIN_REFERRAL_SHARE_BASIS_POINTS..=MAX_REFERRAL_SHARE_BASIS_POINTS).contains(&referral_share_bps),
+            CustomError::InvalidReferralShare
+        );
+
+        ctx.accounts.marketplace_config.referral_share_bps = referral_share_bps;
+        ctx.accounts.marketplace_config.cfish_per_sol = cfish_per_sol;
+
+        msg!("Referral config updated with share: {} bps, rate: {} CFISH per SOL", referral_share_bps, cfish_per_sol);
+        Ok(())
+    }
+
     // Marketplace Program: list_nft instruction
     pub fn list_nft(
         ctx: Context<ListNft>,
         price: u64,
+        payment_mint: Option<Pubkey>, // None for SOL, otherwise the CFISH mint
+        expires_at: Option<i64>,
+        reserved_buyer: Option<Pubkey>, // Only this wallet may buy a private listing
     ) -> Result<()> {
+        if let Some(payment_mint) = payment_mint {
+            require_keys_eq!(payment_mint, ctx.accounts.marketplace_config.cfish_mint, CustomError::UnsupportedPaymentMint);
+        }
+        if let Some(expires_at) = expires_at {
+            require!(expires_at > Clock::get()?.unix_timestamp, CustomError::InvalidListingExpiry);
+        }
+
         // Transfer NFT from seller to escrow account
         let cpi_accounts = Transfer {
             from: ctx.accounts.seller_nft_token_account.to_account_info(),
@@ -51,334 +321,3514 @@ pub mod cfish_contract {
         ctx.accounts.listing.seller = ctx.accounts.seller.key();
         ctx.accounts.listing.nft_mint = ctx.accounts.nft_mint.key();
         ctx.accounts.listing.price = price;
+        ctx.accounts.listing.payment_mint = payment_mint;
         ctx.accounts.listing.escrow_nft_token_account = ctx.accounts.escrow_nft_token_account.key();
         ctx.accounts.listing.escrow_authority = ctx.accounts.escrow_authority.key();
-        ctx.accounts.listing.is_sold = false;
+        ctx.accounts.listing.expires_at = expires_at;
+        ctx.accounts.listing.reserved_buyer = reserved_buyer;
+        ctx.accounts.listing.delegated = false;
+        ctx.accounts.escrow_authority.bump = ctx.bumps.escrow_authority;
 
         msg!("NFT listed for sale at price: {}", price);
         Ok(())
     }
 
-    // Marketplace Program: buy_nft instruction
-    pub fn buy_nft(
-        ctx: Context<BuyNft>,
+    // Marketplace Program: list_nft_delegated instruction
+    // Escrowless listing: the NFT stays in the seller's wallet and the escrow authority
+    // is approved as delegate for it. Revoking the approval voids the listing.
+    // The ATA is not frozen: this program never holds the mint's freeze authority
+    // (Metaplex mints hand it to the edition account).
+    pub fn list_nft_delegated(
+        ctx: Context<ListNftDelegated>,
+        price: u64,
+        payment_mint: Option<Pubkey>, // None for SOL, otherwise the CFISH mint
+        expires_at: Option<i64>,
+        reserved_buyer: Option<Pubkey>, // Only this wallet may buy a private listing
     ) -> Result<()> {
-        let listing = &ctx.accounts.listing;
-        
-        // Transfer SOL from buyer to seller
-        let cpi_accounts = system_program::Transfer {
-            from: ctx.accounts.buyer.to_account_info(),
-            to: ctx.accounts.seller.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.system_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        system_program::transfer(cpi_ctx, listing.price)?;
+        if let Some(payment_mint) = payment_mint {
+            require_keys_eq!(payment_mint, ctx.accounts.marketplace_config.cfish_mint, CustomError::UnsupportedPaymentMint);
+        }
+        if let Some(expires_at) = expires_at {
+            require!(expires_at > Clock::get()?.unix_timestamp, CustomError::InvalidListingExpiry);
+        }
 
-        // Transfer NFT from escrow to buyer
-        let seeds = &[
-            b"escrow_authority",
-            ctx.accounts.listing.nft_mint.as_ref(),
-            &[ctx.accounts.escrow_authority.bump]
-        ];
-        let signer = &[&seeds[..]];
-        
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.escrow_nft_token_account.to_account_info(),
-            to: ctx.accounts.buyer_nft_token_account.to_account_info(),
-            authority: ctx.accounts.escrow_authority.to_account_info(),
+        // Approve the escrow authority as delegate for the NFT
+        let cpi_accounts = Approve {
+            to: ctx.accounts.seller_nft_token_account.to_account_info(),
+            delegate: ctx.accounts.escrow_authority.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        transfer(cpi_ctx, 1)?; // Transfer 1 NFT
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        approve(cpi_ctx, 1)?;
 
-        // Mark listing as sold
-        ctx.accounts.listing.is_sold = true;
+        // Set the listing details
+        ctx.accounts.listing.seller = ctx.accounts.seller.key();
+        ctx.accounts.listing.nft_mint = ctx.accounts.nft_mint.key();
+        ctx.accounts.listing.price = price;
+        ctx.accounts.listing.payment_mint = payment_mint;
+        ctx.accounts.listing.escrow_nft_token_account = ctx.accounts.seller_nft_token_account.key();
+        ctx.accounts.listing.escrow_authority = ctx.accounts.escrow_authority.key();
+        ctx.accounts.listing.expires_at = expires_at;
+        ctx.accounts.listing.reserved_buyer = reserved_buyer;
+        ctx.accounts.listing.delegated = true;
+        ctx.accounts.escrow_authority.bump = ctx.bumps.escrow_authority;
 
-        msg!("NFT purchased successfully");
+        msg!("NFT listed by delegation at price: {}", price);
         Ok(())
     }
 
-    // Staking & Governance Program: stake instruction
-    pub fn stake(
-        ctx: Context<Stake>,
-        amount: u64,
-        duration_days: u64,
+    // Marketplace Program: buy_nft instruction
+    // Creator accounts receiving royalties are passed as remaining accounts,
+    // in the same order as nft_metadata.creators. For CFISH listings these are
+    // the creators' CFISH token accounts instead of their wallets.
+    pub fn buy_nft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyNft<'info>>,
+        max_price: u64, // Guards against a price update landing before the purchase
+        expected_payment_mint: Option<Pubkey>, // None for SOL, otherwise the CFISH mint
     ) -> Result<()> {
-        // Transfer CFISH tokens from staker to stake account
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.staker_token_account.to_account_info(),
-            to: ctx.accounts.stake_account.to_account_info(),
-            authority: ctx.accounts.staker.to_account_info(),
+        require!(ctx.accounts.listing.payment_mint == expected_payment_mint, CustomError::PaymentMintMismatch);
+        let purchase = ListingPurchase {
+            listing: &ctx.accounts.listing,
+            nft_metadata: &ctx.accounts.nft_metadata,
+            escrow_nft_token_account: &ctx.accounts.escrow_nft_token_account,
+            escrow_authority: &ctx.accounts.escrow_authority,
+            buyer: ctx.accounts.buyer.to_account_info(),
+            seller: ctx.accounts.seller.to_account_info(),
+            buyer_nft_token_account: ctx.accounts.buyer_nft_token_account.to_account_info(),
+            buyer_payment_token_account: ctx.accounts.buyer_payment_token_account.as_ref().map(|account| account.to_account_info()),
+            seller_payment_token_account: ctx.accounts.seller_payment_token_account.as_ref().map(|account| account.to_account_info()),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
         };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        transfer(cpi_ctx, amount)?;
+        purchase.validate(max_price, Clock::get()?.unix_timestamp)?;
 
-        // Update stake entry
-        ctx.accounts.stake_entry.staker = ctx.accounts.staker.key();
-        ctx.accounts.stake_entry.amount = ctx.accounts.stake_entry.amount.checked_add(amount).unwrap();
-        ctx.accounts.stake_entry.stake_start_time = Clock::get()?.unix_timestamp;
-        ctx.accounts.stake_entry.duration_days = duration_days;
-        ctx.accounts.stake_entry.claimed_rewards = 0;
+        // The listing and escrow authority are closed to the seller on exit
+        let (fee, total_royalty) = purchase.settle(
+            &ctx.accounts.marketplace_config,
+            &ctx.accounts.treasury,
+            ctx.remaining_accounts,
+        )?;
 
-        msg!("Staked {} CFISH tokens for {} days", amount, duration_days);
+        // Accrue the referrer's share of the platform fee, paid out later in CFISH
+        accrue_referral(
+            &ctx.accounts.referral,
+            ctx.accounts.referral_code.as_mut(),
+            fee,
+            &ctx.accounts.marketplace_config,
+        )?;
+
+        msg!("NFT purchased successfully, platform fee: {}, royalties: {}", fee, total_royalty);
         Ok(())
     }
 
-    // Staking & Governance Program: unstake instruction
-    pub fn unstake(
-        ctx: Context<Unstake>,
+    // Marketplace Program: buy_many instruction
+    // Sweeps several listings priced in the same currency in one transaction, with the
+    // same checks and settlement as buy_nft. Remaining accounts come in groups of
+    // [listing, nft_metadata, escrow_nft_token_account, escrow_authority, seller,
+    // seller_payment_account, buyer_nft_token_account, creators...] with one creator
+    // account per nft_metadata creator. The seller payment account is the seller's CFISH
+    // token account, or the seller wallet again for SOL sweeps. Buyer NFT token accounts
+    // must already exist.
+    pub fn buy_many<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyMany<'info>>,
+        max_total: u64, // Upper bound on the summed price of every purchased listing
+        payment_mint: Option<Pubkey>, // None for SOL, otherwise the CFISH mint
+        skip_if_sold: bool, // Skip unavailable listings instead of failing the whole sweep
     ) -> Result<()> {
-        // Calculate rewards (simplified calculation)
+        let buyer = ctx.accounts.buyer.key();
         let current_time = Clock::get()?.unix_timestamp;
-        let elapsed_days = (current_time - ctx.accounts.stake_entry.stake_start_time) / (24 * 60 * 60);
-        let rewards = (ctx.accounts.stake_entry.amount as f64 * 0.01 * elapsed_days as f64) as u64; // 1% daily reward
 
-        // Transfer CFISH tokens + rewards from stake account to staker
-        let staker_key = ctx.accounts.staker.key();
+        let mut remaining_accounts = ctx.remaining_accounts;
+        let mut total_spent: u64 = 0;
+        let mut total_fee: u64 = 0;
+        let mut purchased: u32 = 0;
+        let mut skipped: u32 = 0;
+        while !remaining_accounts.is_empty() {
+            require!(remaining_accounts.len() >= 7, CustomError::InvalidSweepAccounts);
+            let nft_metadata = Account::<NftMetadata>::try_from(&remaining_accounts[1])?;
+            let group_len = 7 + nft_metadata.creators.len();
+            require!(remaining_accounts.len() >= group_len, CustomError::InvalidSweepAccounts);
+            let (group, rest) = remaining_accounts.split_at(group_len);
+            remaining_accounts = rest;
+            let (seller, buyer_nft_token_account) = (&group[4], &group[6]);
+
+            // Sold or cancelled listings no longer deserialize, since their accounts are closed
+            let accounts = match (
+                Account::<Listing>::try_from(&group[0]),
+                Account::<TokenAccount>::try_from(&group[2]),
+                Account::<EscrowAuthority>::try_from(&group[3]),
+            ) {
+                (Ok(listing), Ok(escrow_nft_token_account), Ok(escrow_authority))
+                    if sweep_accounts_match(
+                        &listing,
+                        &nft_metadata,
+                        escrow_nft_token_account.key(),
+                        escrow_authority.key(),
+                        seller.key(),
+                    ) => Some((listing, escrow_nft_token_account, escrow_authority)),
+                _ => None,
+            };
+            let Some((listing, escrow_nft_token_account, escrow_authority)) = accounts else {
+                require!(skip_if_sold, CustomError::ListingUnavailable);
+                skipped += 1;
+                continue;
+            };
+            // Mixing currencies is a client error, never skipped
+            require!(listing.payment_mint == payment_mint, CustomError::PaymentMintMismatch);
+            require_keys_eq!(
+                buyer_nft_token_account.key(),
+                get_associated_token_address(&buyer, &listing.nft_mint),
+                CustomError::InvalidSweepAccounts
+            );
+
+            let purchase = ListingPurchase {
+                listing: &listing,
+                nft_metadata: &nft_metadata,
+                escrow_nft_token_account: &escrow_nft_token_account,
+                escrow_authority: &escrow_authority,
+                buyer: ctx.accounts.buyer.to_account_info(),
+                seller: seller.clone(),
+                buyer_nft_token_account: buyer_nft_token_account.clone(),
+                buyer_payment_token_account: ctx.accounts.buyer_payment_token_account.as_ref().map(|account| account.to_account_info()),
+                seller_payment_token_account: Some(group[5].clone()),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            };
+            if let Err(error) = purchase.validate(max_total - total_spent, current_time) {
+                if !skip_if_sold {
+                    return Err(error);
+                }
+                skipped += 1;
+                continue;
+            }
+            let (fee, _) = purchase.settle(&ctx.accounts.marketplace_config, &ctx.accounts.treasury, &group[7..])?;
+            total_spent = total_spent.checked_add(listing.price).unwrap();
+            total_fee = total_fee.checked_add(fee).unwrap();
+
+            listing.close(seller.clone())?;
+            escrow_authority.close(seller.clone())?;
+            purchased += 1;
+        }
+
+        // Accrue the referrer's share of the platform fees, paid out later in CFISH
+        accrue_referral(
+            &ctx.accounts.referral,
+            ctx.accounts.referral_code.as_mut(),
+            total_fee,
+            &ctx.accounts.marketplace_config,
+        )?;
+
+        msg!("Swept {} listings for {}, skipped {}", purchased, total_spent, skipped);
+        Ok(())
+    }
+
+    // Marketplace Program: cancel_listing instruction
+    pub fn cancel_listing(
+        ctx: Context<CancelListing>,
+    ) -> Result<()> {
         let seeds = &[
-            b"stake_authority",
-            staker_key.as_ref(),
-            &[ctx.accounts.stake_authority.bump]
+            b"escrow_authority",
+            ctx.accounts.listing.nft_mint.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
         ];
         let signer = &[&seeds[..]];
-        
-        let total_amount = ctx.accounts.stake_entry.amount.checked_add(rewards).unwrap();
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.stake_account.to_account_info(),
-            to: ctx.accounts.staker_token_account.to_account_info(),
-            authority: ctx.accounts.stake_authority.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        transfer(cpi_ctx, total_amount)?;
 
-        // Reset stake entry
-        ctx.accounts.stake_entry.amount = 0;
-        ctx.accounts.stake_entry.claimed_rewards = ctx.accounts.stake_entry.claimed_rewards.checked_add(rewards).unwrap();
+        if ctx.accounts.listing.delegated {
+            // The NFT never left the seller's wallet, just revoke the delegation
+            let cpi_accounts = Revoke {
+                source: ctx.accounts.seller_nft_token_account.to_account_info(),
+                authority: ctx.accounts.seller.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            revoke(cpi_ctx)?;
+        } else {
+            // Return the NFT from escrow to the seller
+            release_escrowed_nft(
+                &ctx.accounts.escrow_nft_token_account.to_account_info(),
+                &ctx.accounts.seller_nft_token_account.to_account_info(),
+                &ctx.accounts.escrow_authority.to_account_info(),
+                &ctx.accounts.seller.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                signer,
+            )?;
+        }
 
-        msg!("Unstaked {} CFISH tokens with {} rewards", ctx.accounts.stake_entry.amount, rewards);
+        msg!("Listing cancelled for NFT: {}", ctx.accounts.listing.nft_mint);
         Ok(())
     }
 
-    // Trading Mining: distribute_reward instruction
-    pub fn distribute_reward(
-        ctx: Context<DistributeReward>,
-        reward_amount: u64,
+    // Marketplace Program: expire_listing instruction
+    // Permissionless crank that returns the NFT of an expired listing to the seller
+    pub fn expire_listing(
+        ctx: Context<ExpireListing>,
     ) -> Result<()> {
-        // Check daily limit (simplified)
-        let current_day = Clock::get()?.unix_timestamp / (24 * 60 * 60);
-        if ctx.accounts.reward_tracker.last_reward_day == current_day {
-            ctx.accounts.reward_tracker.daily_count += 1;
-            require!(ctx.accounts.reward_tracker.daily_count <= 5, CustomError::DailyLimitExceeded);
-        } else {
-            ctx.accounts.reward_tracker.last_reward_day = current_day;
-            ctx.accounts.reward_tracker.daily_count = 1;
-        }
+        let expires_at = ctx.accounts.listing.expires_at.ok_or(CustomError::ListingNotExpired)?;
+        require!(Clock::get()?.unix_timestamp >= expires_at, CustomError::ListingNotExpired);
 
-        // Create vesting entry for reward
-        ctx.accounts.vesting_entry.beneficiary = ctx.accounts.user.key();
-        ctx.accounts.vesting_entry.total_amount = reward_amount;
-        ctx.accounts.vesting_entry.released_amount = 0;
-        ctx.accounts.vesting_entry.start_time = Clock::get()?.unix_timestamp + (24 * 60 * 60); // Start tomorrow
-        ctx.accounts.vesting_entry.duration = 180 * 24 * 60 * 60; // 180 days
+        let seeds = &[
+            b"escrow_authority",
+            ctx.accounts.listing.nft_mint.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
 
-        msg!("Reward of {} CFISH distributed to user", reward_amount);
+        // Delegated listings keep the NFT in the seller's wallet, only the listing is closed
+        if !ctx.accounts.listing.delegated {
+            // Return the NFT from escrow to the seller
+            release_escrowed_nft(
+                &ctx.accounts.escrow_nft_token_account.to_account_info(),
+                &ctx.accounts.seller_nft_token_account.to_account_info(),
+                &ctx.accounts.escrow_authority.to_account_info(),
+                &ctx.accounts.seller.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                signer,
+            )?;
+        }
+
+        msg!("Expired listing closed for NFT: {}", ctx.accounts.listing.nft_mint);
         Ok(())
     }
 
-    // Governance: create_proposal instruction
-    pub fn create_proposal(
-        ctx: Context<CreateProposal>,
-        title: String,
-        description: String,
-        voting_period: i64,
+    // Marketplace Program: close_revoked_listing instruction
+    // Permissionless crank that closes a delegated listing once the seller revoked the
+    // approval, moved the NFT or closed the token account, refunding the rent to the seller
+    pub fn close_revoked_listing(
+        ctx: Context<CloseRevokedListing>,
     ) -> Result<()> {
-        ctx.accounts.proposal.proposer = ctx.accounts.proposer.key();
-        ctx.accounts.proposal.title = title;
-        ctx.accounts.proposal.description = description;
-        ctx.accounts.proposal.start_time = Clock::get()?.unix_timestamp;
-        ctx.accounts.proposal.end_time = Clock::get()?.unix_timestamp + voting_period;
-        ctx.accounts.proposal.yes_votes = 0;
-        ctx.accounts.proposal.no_votes = 0;
-        ctx.accounts.proposal.executed = false;
-        ctx.accounts.proposal.bump = ctx.bumps.proposal;
-        msg!("Proposal created: {}", ctx.accounts.proposal.title);
+        // A closed token account no longer deserializes
+        let token_account = &ctx.accounts.escrow_nft_token_account;
+        let revoked = token_account.owner != &Token::id()
+            || match TokenAccount::try_deserialize(&mut &token_account.try_borrow_data()?[..]) {
+                Ok(token_account) => !delegation_intact(&token_account, ctx.accounts.escrow_authority.key()),
+                Err(_) => true,
+            };
+        require!(revoked, CustomError::ListingDelegationIntact);
+
+        msg!("Revoked listing closed for NFT: {}", ctx.accounts.listing.nft_mint);
         Ok(())
     }
 
-    // Governance: vote instruction
-    pub fn vote(
-        ctx: Context<Vote>,
-        vote_yes: bool,
+    // Marketplace Program: update_listing_price instruction
+    pub fn update_listing_price(
+        ctx: Context<UpdateListingPrice>,
+        new_price: u64,
+        expected_current_price: Option<u64>, // Guards against racing a buyer
     ) -> Result<()> {
-        let voting_power = ctx.accounts.stake_entry.amount; // Voting power based on staked amount
-        
-        if vote_yes {
-            ctx.accounts.proposal.yes_votes = ctx.accounts.proposal.yes_votes.checked_add(voting_power).unwrap();
-        } else {
-            ctx.accounts.proposal.no_votes = ctx.accounts.proposal.no_votes.checked_add(voting_power).unwrap();
+        let old_price = ctx.accounts.listing.price;
+        if let Some(expected_current_price) = expected_current_price {
+            require!(old_price == expected_current_price, CustomError::ListingPriceChanged);
         }
 
-        ctx.accounts.vote_record.voter = ctx.accounts.voter.key();
-        ctx.accounts.vote_record.proposal = ctx.accounts.proposal.key();
-        ctx.accounts.vote_record.vote_yes = vote_yes;
-        ctx.accounts.vote_record.voting_power = voting_power;
+        ctx.accounts.listing.price = new_price;
 
-        msg!("Vote cast: {} with power {}", if vote_yes { "YES" } else { "NO" }, voting_power);
+        msg!("Listing price updated from {} to {}", old_price, new_price);
         Ok(())
     }
 
-    // Tokenomics: release_vested_reward instruction
-    pub fn release_vested_reward(
-        ctx: Context<ReleaseVestedReward>,
+    // Marketplace Program: create_auction instruction
+    // English auction settled in SOL. Bids are escrowed in the auction account itself.
+    pub fn create_auction(
+        ctx: Context<CreateAuction>,
+        reserve_price: u64,
+        min_bid_increment: u64,
+        duration: i64,
+        extension_window: i64, // Bids this close to the end push the end time out by this much
     ) -> Result<()> {
-        let current_time = Clock::get()?.unix_timestamp;
-        let vesting_entry = &mut ctx.accounts.vesting_entry;
+        require!(duration > 0 && extension_window >= 0, CustomError::InvalidAuctionDuration);
+        require!(min_bid_increment > 0, CustomError::InvalidBidIncrement);
+        // Reject auctions whose fee and royalty rates add up to more than any winning bid
+        let fees = sale_fees(
+            reserve_price,
+            ctx.accounts.marketplace_config.fee_basis_points,
+            &ctx.accounts.nft_metadata,
+        );
+        require!(
+            fees <= reserve_price
+                && ctx.accounts.nft_metadata.seller_fee_basis_points as u32
+                    + ctx.accounts.marketplace_config.fee_basis_points as u32 <= 10_000,
+            CustomError::AuctionCannotSettle
+        );
 
-        require!(current_time >= vesting_entry.start_time, CustomError::VestingNotStarted);
-
-        let total_duration = vesting_entry.duration;
-        let elapsed_duration = current_time - vesting_entry.start_time;
+        // Transfer NFT from seller to escrow account
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.seller_nft_token_account.to_account_info(),
+            to: ctx.accounts.escrow_nft_token_account.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer(cpi_ctx, 1)?; // Transfer 1 NFT
 
-        let vested_amount = (vesting_entry.total_amount as f64 * (elapsed_duration as f64 / total_duration as f64)) as u64;
-        let amount_to_release = vested_amount.checked_sub(vesting_entry.released_amount).unwrap();
+        let current_time = Clock::get()?.unix_timestamp;
+        let auction = &mut ctx.accounts.auction;
+        auction.seller = ctx.accounts.seller.key();
+        auction.nft_mint = ctx.accounts.nft_mint.key();
+        auction.escrow_nft_token_account = ctx.accounts.escrow_nft_token_account.key();
+        auction.escrow_authority = ctx.accounts.escrow_authority.key();
+        auction.reserve_price = reserve_price;
+        auction.min_bid_increment = min_bid_increment;
+        auction.highest_bid = 0;
+        auction.highest_bidder = None;
+        auction.start_time = current_time;
+        auction.end_time = current_time.checked_add(duration).unwrap();
+        auction.extension_window = extension_window;
+        auction.bump = ctx.bumps.auction;
+        ctx.accounts.escrow_authority.bump = ctx.bumps.escrow_authority;
 
-        require!(amount_to_release > 0, CustomError::NoRewardsToRelease);
+        msg!("Auction created with reserve price: {}", reserve_price);
+        Ok(())
+    }
+
+    // Marketplace Program: place_bid instruction
+    // The previous highest bidder must be passed so they can be refunded
+    pub fn place_bid(
+        ctx: Context<PlaceBid>,
+        amount: u64,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let auction = &ctx.accounts.auction;
+        require!(current_time < auction.end_time, CustomError::AuctionEnded);
+        match auction.highest_bidder {
+            Some(_) => {
+                let min_bid = auction.highest_bid.checked_add(auction.min_bid_increment).unwrap();
+                require!(amount >= min_bid, CustomError::BidTooLow);
+            }
+            None => require!(amount > 0 && amount >= auction.reserve_price, CustomError::BidTooLow),
+        }
+
+        // Escrow the new bid in the auction account
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.bidder.to_account_info(),
+            to: ctx.accounts.auction.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.system_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        system_program::transfer(cpi_ctx, amount)?;
+
+        // Refund the outbid bidder
+        if let Some(previous_bidder) = ctx.accounts.auction.highest_bidder {
+            let previous_bidder_account = ctx.accounts.previous_bidder.as_ref()
+                .ok_or(CustomError::PreviousBidderMismatch)?;
+            require_keys_eq!(previous_bidder_account.key(), previous_bidder, CustomError::PreviousBidderMismatch);
+            let source = PaymentSource::Escrow {
+                account: ctx.accounts.auction.to_account_info(),
+            };
+            source.pay(&previous_bidder_account.to_account_info(), ctx.accounts.auction.highest_bid)?;
+        }
+
+        let auction = &mut ctx.accounts.auction;
+        auction.highest_bid = amount;
+        auction.highest_bidder = Some(ctx.accounts.bidder.key());
+
+        // Anti-sniping: a late bid extends the auction
+        if auction.end_time - current_time < auction.extension_window {
+            auction.end_time = current_time + auction.extension_window;
+        }
+
+        msg!("Bid of {} placed, auction ends at {}", amount, auction.end_time);
+        Ok(())
+    }
+
+    // Marketplace Program: settle_auction instruction
+    // Permissionless once the auction has ended. Creator accounts for royalties are
+    // passed as remaining accounts, in the same order as nft_metadata.creators.
+    pub fn settle_auction<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleAuction<'info>>,
+    ) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        require!(Clock::get()?.unix_timestamp >= auction.end_time, CustomError::AuctionNotEnded);
+
+        // The NFT goes to the winner, or back to the seller if nobody bid
+        let recipient = auction.highest_bidder.unwrap_or(auction.seller);
+        require_keys_eq!(ctx.accounts.recipient.key(), recipient, CustomError::AuctionRecipientMismatch);
 
-        // Transfer vested tokens from authority to beneficiary
-        let authority_key = ctx.accounts.authority.key();
         let seeds = &[
-            b"authority",
-            authority_key.as_ref(),
-            &[ctx.accounts.authority.bump]
+            b"escrow_authority",
+            auction.nft_mint.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+
+        release_escrowed_nft(
+            &ctx.accounts.escrow_nft_token_account.to_account_info(),
+            &ctx.accounts.recipient_nft_token_account.to_account_info(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            signer,
+        )?;
+
+        // Pay out the winning bid from the auction account
+        if auction.highest_bidder.is_some() {
+            let source = PaymentSource::Escrow {
+                account: auction.to_account_info(),
+            };
+            let (fee, total_royalty) = settle_sale(
+                &source,
+                auction.highest_bid,
+                ctx.accounts.marketplace_config.fee_basis_points,
+                &ctx.accounts.treasury,
+                &ctx.accounts.nft_metadata,
+                ctx.remaining_accounts,
+                &ctx.accounts.seller,
+            )?;
+            msg!("Auction settled at {}, platform fee: {}, royalties: {}", auction.highest_bid, fee, total_royalty);
+        } else {
+            msg!("Auction ended without bids, NFT returned to seller");
+        }
+        Ok(())
+    }
+
+    // Marketplace Program: cancel_auction instruction
+    pub fn cancel_auction(
+        ctx: Context<CancelAuction>,
+    ) -> Result<()> {
+        require!(ctx.accounts.auction.highest_bidder.is_none(), CustomError::AuctionHasBids);
+
+        let seeds = &[
+            b"escrow_authority",
+            ctx.accounts.auction.nft_mint.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+
+        // Return the NFT from escrow to the seller
+        release_escrowed_nft(
+            &ctx.accounts.escrow_nft_token_account.to_account_info(),
+            &ctx.accounts.seller_nft_token_account.to_account_info(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            signer,
+        )?;
+
+        msg!("Auction cancelled for NFT: {}", ctx.accounts.auction.nft_mint);
+        Ok(())
+    }
+
+    // Marketplace Program: unwind_auction instruction
+    // Permissionless once the auction has ended. If the platform fee and royalties exceed the
+    // winning bid, settle_auction cannot pay out, so the bid is refunded and the NFT returned.
+    pub fn unwind_auction(
+        ctx: Context<UnwindAuction>,
+    ) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        require!(Clock::get()?.unix_timestamp >= auction.end_time, CustomError::AuctionNotEnded);
+        let highest_bidder = auction.highest_bidder.ok_or(CustomError::AuctionSettleable)?;
+        require_keys_eq!(ctx.accounts.highest_bidder.key(), highest_bidder, CustomError::AuctionRecipientMismatch);
+        let fees = sale_fees(
+            auction.highest_bid,
+            ctx.accounts.marketplace_config.fee_basis_points,
+            &ctx.accounts.nft_metadata,
+        );
+        require!(fees > auction.highest_bid, CustomError::AuctionSettleable);
+
+        let seeds = &[
+            b"escrow_authority",
+            auction.nft_mint.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
         ];
         let signer = &[&seeds[..]];
 
+        // Return the NFT from escrow to the seller
+        release_escrowed_nft(
+            &ctx.accounts.escrow_nft_token_account.to_account_info(),
+            &ctx.accounts.seller_nft_token_account.to_account_info(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            signer,
+        )?;
+
+        // Refund the highest bid from the auction account
+        let source = PaymentSource::Escrow {
+            account: auction.to_account_info(),
+        };
+        source.pay(&ctx.accounts.highest_bidder, auction.highest_bid)?;
+
+        msg!("Auction unwound, bid of {} refunded and NFT returned to seller", auction.highest_bid);
+        Ok(())
+    }
+
+    // Marketplace Program: list_dutch instruction
+    // Dutch auction settled in SOL. The price decays from start_price to floor_price
+    // over the duration, linearly or in steps of step_duration seconds.
+    pub fn list_dutch(
+        ctx: Context<ListDutch>,
+        start_price: u64,
+        floor_price: u64,
+        duration: i64,
+        step_duration: i64, // 0 for a linear decay
+    ) -> Result<()> {
+        require!(
+            start_price >= floor_price && duration > 0 && step_duration >= 0,
+            CustomError::InvalidDutchAuction
+        );
+
+        // Transfer NFT from seller to escrow account
         let cpi_accounts = Transfer {
-            from: ctx.accounts.authority_token_account.to_account_info(),
-            to: ctx.accounts.beneficiary_token_account.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
+            from: ctx.accounts.seller_nft_token_account.to_account_info(),
+            to: ctx.accounts.escrow_nft_token_account.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
         };
         let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
-        transfer(cpi_ctx, amount_to_release)?;
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer(cpi_ctx, 1)?; // Transfer 1 NFT
 
-        vesting_entry.released_amount = vesting_entry.released_amount.checked_add(amount_to_release).unwrap();
+        let current_time = Clock::get()?.unix_timestamp;
+        let dutch_listing = &mut ctx.accounts.dutch_listing;
+        dutch_listing.seller = ctx.accounts.seller.key();
+        dutch_listing.nft_mint = ctx.accounts.nft_mint.key();
+        dutch_listing.escrow_nft_token_account = ctx.accounts.escrow_nft_token_account.key();
+        dutch_listing.escrow_authority = ctx.accounts.escrow_authority.key();
+        dutch_listing.start_price = start_price;
+        dutch_listing.floor_price = floor_price;
+        dutch_listing.start_time = current_time;
+        dutch_listing.end_time = current_time.checked_add(duration).unwrap();
+        dutch_listing.step_duration = step_duration;
+        dutch_listing.bump = ctx.bumps.dutch_listing;
+        ctx.accounts.escrow_authority.bump = ctx.bumps.escrow_authority;
 
-        msg!("Released {} vested CFISH tokens", amount_to_release);
+        msg!("Dutch auction listed from {} down to {}", start_price, floor_price);
+        Ok(())
+    }
+
+    // Marketplace Program: buy_dutch instruction
+    // Creator accounts receiving royalties are passed as remaining accounts,
+    // in the same order as nft_metadata.creators
+    pub fn buy_dutch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyDutch<'info>>,
+        max_price: u64,
+    ) -> Result<()> {
+        let dutch_listing = &ctx.accounts.dutch_listing;
+        let price = dutch_price(dutch_listing, Clock::get()?.unix_timestamp);
+        require!(price <= max_price, CustomError::PriceExceedsMax);
+
+        let source = PaymentSource::Wallet {
+            payer: ctx.accounts.buyer.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+        let (fee, total_royalty) = settle_sale(
+            &source,
+            price,
+            ctx.accounts.marketplace_config.fee_basis_points,
+            &ctx.accounts.treasury,
+            &ctx.accounts.nft_metadata,
+            ctx.remaining_accounts,
+            &ctx.accounts.seller,
+        )?;
+
+        // Transfer NFT from escrow to buyer
+        let seeds = &[
+            b"escrow_authority",
+            dutch_listing.nft_mint.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+
+        release_escrowed_nft(
+            &ctx.accounts.escrow_nft_token_account.to_account_info(),
+            &ctx.accounts.buyer_nft_token_account.to_account_info(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            signer,
+        )?;
+
+        msg!("Dutch auction sold at {}, platform fee: {}, royalties: {}", price, fee, total_royalty);
+        Ok(())
+    }
+
+    // Marketplace Program: cancel_dutch instruction
+    pub fn cancel_dutch(
+        ctx: Context<CancelDutch>,
+    ) -> Result<()> {
+        let seeds = &[
+            b"escrow_authority",
+            ctx.accounts.dutch_listing.nft_mint.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+
+        // Return the NFT from escrow to the seller
+        release_escrowed_nft(
+            &ctx.accounts.escrow_nft_token_account.to_account_info(),
+            &ctx.accounts.seller_nft_token_account.to_account_info(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            signer,
+        )?;
+
+        msg!("Dutch auction cancelled for NFT: {}", ctx.accounts.dutch_listing.nft_mint);
+        Ok(())
+    }
+
+    // Marketplace Program: make_offer instruction
+    // Offers on any NFT, listed or not. SOL is escrowed in the offer account itself,
+    // CFISH in a token vault owned by the offer.
+    pub fn make_offer(
+        ctx: Context<MakeOffer>,
+        price: u64,
+    ) -> Result<()> {
+        let payment_mint = ctx.accounts.payment_mint.as_ref().map(|mint| mint.key());
+        if payment_mint.is_some() {
+            let buyer_payment_token_account = ctx.accounts.buyer_payment_token_account.as_ref()
+                .ok_or(CustomError::MissingPaymentAccount)?;
+            let offer_vault = ctx.accounts.offer_vault.as_ref().ok_or(CustomError::MissingPaymentAccount)?;
+            let cpi_accounts = Transfer {
+                from: buyer_payment_token_account.to_account_info(),
+                to: offer_vault.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            transfer(cpi_ctx, price)?;
+        } else {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.buyer.to_account_info(),
+                to: ctx.accounts.offer.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.system_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            system_program::transfer(cpi_ctx, price)?;
+        }
+
+        ctx.accounts.offer.buyer = ctx.accounts.buyer.key();
+        ctx.accounts.offer.nft_mint = ctx.accounts.nft_mint.key();
+        ctx.accounts.offer.price = price;
+        ctx.accounts.offer.payment_mint = payment_mint;
+        ctx.accounts.offer.bump = ctx.bumps.offer;
+
+        msg!("Offer of {} made on NFT: {}", price, ctx.accounts.nft_mint.key());
+        Ok(())
+    }
+
+    // Marketplace Program: cancel_offer instruction
+    pub fn cancel_offer(
+        ctx: Context<CancelOffer>,
+    ) -> Result<()> {
+        // SOL offers are refunded when the offer account is closed to the buyer
+        if ctx.accounts.offer.payment_mint.is_some() {
+            let offer_vault = ctx.accounts.offer_vault.as_ref().ok_or(CustomError::MissingPaymentAccount)?;
+            let buyer_payment_token_account = ctx.accounts.buyer_payment_token_account.as_ref()
+                .ok_or(CustomError::MissingPaymentAccount)?;
+            let offer = &ctx.accounts.offer;
+            let seeds = &[
+                b"offer",
+                offer.nft_mint.as_ref(),
+                offer.buyer.as_ref(),
+                &[offer.bump]
+            ];
+            let signer = &[&seeds[..]];
+
+            let cpi_accounts = Transfer {
+                from: offer_vault.to_account_info(),
+                to: buyer_payment_token_account.to_account_info(),
+                authority: offer.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            transfer(cpi_ctx, offer.price)?;
+
+            let cpi_accounts = CloseAccount {
+                account: offer_vault.to_account_info(),
+                destination: ctx.accounts.buyer.to_account_info(),
+                authority: offer.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            close_account(cpi_ctx)?;
+        }
+
+        msg!("Offer cancelled on NFT: {}", ctx.accounts.offer.nft_mint);
+        Ok(())
+    }
+
+    // Marketplace Program: accept_offer instruction
+    // The NFT owner transfers the token straight to the offerer and is paid from the
+    // escrow with the same fee and royalty split as buy_nft. Creator accounts receiving
+    // royalties are passed as remaining accounts, in the same order as nft_metadata.creators.
+    pub fn accept_offer<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AcceptOffer<'info>>,
+    ) -> Result<()> {
+        // Transfer NFT from owner to offerer
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.seller_nft_token_account.to_account_info(),
+            to: ctx.accounts.buyer_nft_token_account.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer(cpi_ctx, 1)?; // Transfer 1 NFT
+
+        let offer = &ctx.accounts.offer;
+        let seeds = &[
+            b"offer",
+            offer.nft_mint.as_ref(),
+            offer.buyer.as_ref(),
+            &[offer.bump]
+        ];
+        let signer = &[&seeds[..]];
+
+        // Settle from the SOL held in the offer, or from the CFISH vault
+        let (source, seller, fee_basis_points) = match offer.payment_mint {
+            Some(payment_mint) => {
+                let offer_vault = ctx.accounts.offer_vault.as_ref().ok_or(CustomError::MissingPaymentAccount)?;
+                let seller_payment_token_account = ctx.accounts.seller_payment_token_account.as_ref()
+                    .ok_or(CustomError::MissingPaymentAccount)?;
+                let source = PaymentSource::Token {
+                    from: offer_vault.to_account_info(),
+                    authority: offer.to_account_info(),
+                    mint: payment_mint,
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                    signer_seeds: signer,
+                };
+                // CFISH settlements are exempt from the platform fee
+                (source, seller_payment_token_account.to_account_info(), 0)
+            }
+            None => {
+                let source = PaymentSource::Escrow {
+                    account: offer.to_account_info(),
+                };
+                (source, ctx.accounts.seller.to_account_info(), ctx.accounts.marketplace_config.fee_basis_points)
+            }
+        };
+
+        let (fee, total_royalty) = settle_sale(
+            &source,
+            offer.price,
+            fee_basis_points,
+            &ctx.accounts.treasury,
+            &ctx.accounts.nft_metadata,
+            ctx.remaining_accounts,
+            &seller,
+        )?;
+
+        // Close the empty CFISH vault and refund its rent to the offerer
+        if let Some(offer_vault) = &ctx.accounts.offer_vault {
+            let cpi_accounts = CloseAccount {
+                account: offer_vault.to_account_info(),
+                destination: ctx.accounts.buyer.to_account_info(),
+                authority: offer.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+            close_account(cpi_ctx)?;
+        }
+
+        msg!("Offer accepted at {}, platform fee: {}, royalties: {}", offer.price, fee, total_royalty);
+        Ok(())
+    }
+
+    // Marketplace Program: make_collection_offer instruction
+    // Escrows SOL for `quantity` NFTs of a collection at a fixed price per item
+    pub fn make_collection_offer(
+        ctx: Context<MakeCollectionOffer>,
+        collection: Pubkey,
+        price: u64,
+        quantity: u64,
+    ) -> Result<()> {
+        require!(quantity > 0, CustomError::InvalidOfferQuantity);
+
+        // Escrow the SOL for every item in the offer account
+        let total = price.checked_mul(quantity).unwrap();
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.buyer.to_account_info(),
+            to: ctx.accounts.collection_offer.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.system_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        system_program::transfer(cpi_ctx, total)?;
+
+        ctx.accounts.collection_offer.buyer = ctx.accounts.buyer.key();
+        ctx.accounts.collection_offer.collection = collection;
+        ctx.accounts.collection_offer.price = price;
+        ctx.accounts.collection_offer.quantity = quantity;
+        ctx.accounts.collection_offer.bump = ctx.bumps.collection_offer;
+
+        msg!("Collection offer of {} for {} items made on {}", price, quantity, collection);
+        Ok(())
+    }
+
+    // Marketplace Program: cancel_collection_offer instruction
+    // The remaining escrow is refunded when the offer account is closed to the buyer
+    pub fn cancel_collection_offer(
+        ctx: Context<CancelCollectionOffer>,
+    ) -> Result<()> {
+        msg!("Collection offer cancelled with {} items remaining", ctx.accounts.collection_offer.quantity);
+        Ok(())
+    }
+
+    // Marketplace Program: fill_collection_offer instruction
+    // Any holder of a verified member of the collection can sell into the offer.
+    // Creator accounts receiving royalties are passed as remaining accounts,
+    // in the same order as nft_metadata.creators.
+    pub fn fill_collection_offer<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FillCollectionOffer<'info>>,
+    ) -> Result<()> {
+        // Transfer NFT from holder to offerer
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.seller_nft_token_account.to_account_info(),
+            to: ctx.accounts.buyer_nft_token_account.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer(cpi_ctx, 1)?; // Transfer 1 NFT
+
+        // Pay for one item out of the escrow
+        let source = PaymentSource::Escrow {
+            account: ctx.accounts.collection_offer.to_account_info(),
+        };
+        let (fee, total_royalty) = settle_sale(
+            &source,
+            ctx.accounts.collection_offer.price,
+            ctx.accounts.marketplace_config.fee_basis_points,
+            &ctx.accounts.treasury,
+            &ctx.accounts.nft_metadata,
+            ctx.remaining_accounts,
+            &ctx.accounts.seller.to_account_info(),
+        )?;
+
+        let collection_offer = &mut ctx.accounts.collection_offer;
+        collection_offer.quantity -= 1;
+        msg!(
+            "Collection offer filled at {}, platform fee: {}, royalties: {}, {} items remaining",
+            collection_offer.price, fee, total_royalty, collection_offer.quantity
+        );
+
+        // Close the offer once every item has been bought
+        if collection_offer.quantity == 0 {
+            collection_offer.close(ctx.accounts.buyer.to_account_info())?;
+        }
+        Ok(())
+    }
+
+    // Marketplace Program: list_bundle instruction
+    // Escrows several NFTs under one SOL price. Remaining accounts come in
+    // [nft_mint, seller_nft_token_account, escrow_nft_token_account] triples, where the
+    // escrow account is the escrow authority's associated token account, created here,
+    // followed by the nft_metadata account of every NFT in the same order.
+    pub fn list_bundle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ListBundle<'info>>,
+        bundle_id: u64,
+        price: u64,
+    ) -> Result<()> {
+        require!(ctx.remaining_accounts.len().is_multiple_of(4), CustomError::InvalidBundleSize);
+        let bundle_size = ctx.remaining_accounts.len() / 4;
+        require!(bundle_size > 0 && bundle_size <= MAX_BUNDLE_SIZE, CustomError::InvalidBundleSize);
+        let (token_accounts, metadata_accounts) = ctx.remaining_accounts.split_at(bundle_size * 3);
+
+        let nft_mints = escrow_nfts(
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.associated_token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            token_accounts,
+        )?;
+
+        // Only NFTs minted by this program can be sold, so buy_bundle can pay royalties
+        for (nft_mint, metadata_account) in nft_mints.iter().zip(metadata_accounts) {
+            let nft_metadata = Account::<NftMetadata>::try_from(metadata_account)?;
+            require_keys_eq!(nft_metadata.mint, *nft_mint, CustomError::BundleMetadataMismatch);
+        }
+
+        let bundle = &mut ctx.accounts.bundle;
+        bundle.seller = ctx.accounts.seller.key();
+        bundle.bundle_id = bundle_id;
+        bundle.price = price;
+        bundle.nft_mints = nft_mints;
+        bundle.escrow_authority = ctx.accounts.escrow_authority.key();
+        bundle.bump = ctx.bumps.bundle;
+        ctx.accounts.escrow_authority.bump = ctx.bumps.escrow_authority;
+
+        msg!("Bundle of {} NFTs listed for sale at price: {}", bundle_size, price);
+        Ok(())
+    }
+
+    // Marketplace Program: buy_bundle instruction
+    // The bundle price is split evenly between its NFTs, the remainder going to the last
+    // one, and each share is settled like buy_nft with its own royalties. Remaining
+    // accounts come in [escrow_nft_token_account, buyer_nft_token_account] pairs, then
+    // [nft_metadata, creators...] groups with one creator account per nft_metadata
+    // creator, both in bundle.nft_mints order. The buyer's associated token accounts
+    // must already exist.
+    pub fn buy_bundle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyBundle<'info>>,
+    ) -> Result<()> {
+        let bundle = &ctx.accounts.bundle;
+        let bundle_size = bundle.nft_mints.len();
+        require!(ctx.remaining_accounts.len() >= bundle_size * 2, CustomError::BundleAccountMismatch);
+        let (token_accounts, mut royalty_accounts) = ctx.remaining_accounts.split_at(bundle_size * 2);
+
+        let source = PaymentSource::Wallet {
+            payer: ctx.accounts.buyer.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+        let item_price = bundle.price / bundle_size as u64;
+        let mut total_fee: u64 = 0;
+        let mut total_royalty: u64 = 0;
+        for (i, nft_mint) in bundle.nft_mints.iter().enumerate() {
+            require!(!royalty_accounts.is_empty(), CustomError::BundleMetadataMismatch);
+            let nft_metadata = Account::<NftMetadata>::try_from(&royalty_accounts[0])?;
+            require_keys_eq!(nft_metadata.mint, *nft_mint, CustomError::BundleMetadataMismatch);
+            let group_len = 1 + nft_metadata.creators.len();
+            require!(royalty_accounts.len() >= group_len, CustomError::CreatorMismatch);
+            let (group, rest) = royalty_accounts.split_at(group_len);
+            royalty_accounts = rest;
+
+            let price = if i + 1 == bundle_size {
+                bundle.price - item_price * (bundle_size as u64 - 1)
+            } else {
+                item_price
+            };
+            let (fee, royalty) = settle_sale(
+                &source,
+                price,
+                ctx.accounts.marketplace_config.fee_basis_points,
+                &ctx.accounts.treasury,
+                &nft_metadata,
+                &group[1..],
+                &ctx.accounts.seller,
+            )?;
+            total_fee = total_fee.checked_add(fee).unwrap();
+            total_royalty = total_royalty.checked_add(royalty).unwrap();
+        }
+
+        // Transfer every NFT from escrow to buyer
+        let bundle_key = bundle.key();
+        let seeds = &[
+            b"escrow_authority",
+            bundle_key.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+        release_escrowed_nfts(
+            &bundle.nft_mints,
+            ctx.accounts.buyer.key(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.seller,
+            &ctx.accounts.token_program.to_account_info(),
+            token_accounts,
+            signer,
+            CustomError::BundleAccountMismatch,
+        )?;
+
+        msg!("Bundle purchased successfully, platform fee: {}, royalties: {}", total_fee, total_royalty);
+        Ok(())
+    }
+
+    // Marketplace Program: cancel_bundle instruction
+    // Remaining accounts come in [escrow_nft_token_account, seller_nft_token_account]
+    // pairs, in bundle.nft_mints order
+    pub fn cancel_bundle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelBundle<'info>>,
+    ) -> Result<()> {
+        let bundle = &ctx.accounts.bundle;
+        let bundle_key = bundle.key();
+        let seeds = &[
+            b"escrow_authority",
+            bundle_key.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+        release_escrowed_nfts(
+            &bundle.nft_mints,
+            ctx.accounts.seller.key(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.remaining_accounts,
+            signer,
+            CustomError::BundleAccountMismatch,
+        )?;
+
+        msg!("Bundle {} cancelled", bundle.bundle_id);
+        Ok(())
+    }
+
+    // Barter Program: create_swap instruction
+    // Escrows the initiator's NFTs and names the counterparty NFTs wanted in return.
+    // Remaining accounts come in [nft_mint, initiator_nft_token_account,
+    // escrow_nft_token_account] triples, one per offered NFT. Top-ups are in SOL, or in
+    // CFISH when the payment mint is passed.
+    pub fn create_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateSwap<'info>>,
+        swap_id: u64,
+        counterparty: Pubkey,
+        requested_mints: Vec<Pubkey>,
+        initiator_topup: u64, // Escrowed now, paid to the counterparty on acceptance
+        counterparty_topup: u64, // Paid by the counterparty to the initiator on acceptance
+    ) -> Result<()> {
+        require!(ctx.remaining_accounts.len().is_multiple_of(3), CustomError::InvalidSwapSize);
+        let offered_count = ctx.remaining_accounts.len() / 3;
+        require!(offered_count > 0 && offered_count <= MAX_SWAP_SIZE, CustomError::InvalidSwapSize);
+        require!(!requested_mints.is_empty() && requested_mints.len() <= MAX_SWAP_SIZE, CustomError::InvalidSwapSize);
+        require_keys_neq!(counterparty, ctx.accounts.initiator.key(), CustomError::InvalidSwapCounterparty);
+
+        let offered_mints = escrow_nfts(
+            &ctx.accounts.initiator.to_account_info(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.associated_token_program.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
+
+        // Escrow the initiator's top-up in the CFISH vault, or as SOL in the swap account
+        let topup_mint = ctx.accounts.payment_mint.as_ref().map(|mint| mint.key());
+        if topup_mint.is_some() {
+            let initiator_payment_token_account = ctx.accounts.initiator_payment_token_account.as_ref()
+                .ok_or(CustomError::MissingPaymentAccount)?;
+            let swap_vault = ctx.accounts.swap_vault.as_ref().ok_or(CustomError::MissingPaymentAccount)?;
+            let cpi_accounts = Transfer {
+                from: initiator_payment_token_account.to_account_info(),
+                to: swap_vault.to_account_info(),
+                authority: ctx.accounts.initiator.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            transfer(cpi_ctx, initiator_topup)?;
+        } else if initiator_topup > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.initiator.to_account_info(),
+                to: ctx.accounts.swap.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.system_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            system_program::transfer(cpi_ctx, initiator_topup)?;
+        }
+
+        let swap = &mut ctx.accounts.swap;
+        swap.initiator = ctx.accounts.initiator.key();
+        swap.counterparty = counterparty;
+        swap.swap_id = swap_id;
+        swap.offered_mints = offered_mints;
+        swap.requested_mints = requested_mints;
+        swap.topup_mint = topup_mint;
+        swap.initiator_topup = initiator_topup;
+        swap.counterparty_topup = counterparty_topup;
+        swap.escrow_authority = ctx.accounts.escrow_authority.key();
+        swap.bump = ctx.bumps.swap;
+        ctx.accounts.escrow_authority.bump = ctx.bumps.escrow_authority;
+
+        msg!(
+            "Swap {} proposed: {} NFTs plus {} offered for {} NFTs plus {}",
+            swap_id,
+            offered_count,
+            initiator_topup,
+            swap.requested_mints.len(),
+            counterparty_topup
+        );
+        Ok(())
+    }
+
+    // Barter Program: accept_swap instruction
+    // Moves both sides in one transaction. Remaining accounts come in
+    // [escrow_nft_token_account, counterparty_nft_token_account] pairs in swap.offered_mints
+    // order, followed by [counterparty_nft_token_account, initiator_nft_token_account] pairs
+    // in swap.requested_mints order. Recipient associated token accounts must already exist.
+    // Top-ups are exchanged in the same transaction.
+    pub fn accept_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AcceptSwap<'info>>,
+    ) -> Result<()> {
+        let swap = &ctx.accounts.swap;
+        let offered_accounts_len = swap.offered_mints.len() * 2;
+        require!(
+            ctx.remaining_accounts.len() == offered_accounts_len + swap.requested_mints.len() * 2,
+            CustomError::SwapAccountMismatch
+        );
+        let (offered_accounts, requested_accounts) = ctx.remaining_accounts.split_at(offered_accounts_len);
+
+        // Transfer the requested NFTs from the counterparty to the initiator
+        for (nft_mint, accounts) in swap.requested_mints.iter().zip(requested_accounts.chunks(2)) {
+            let (counterparty_nft_token_account, initiator_nft_token_account) = (&accounts[0], &accounts[1]);
+            require_keys_eq!(
+                initiator_nft_token_account.key(),
+                get_associated_token_address(&swap.initiator, nft_mint),
+                CustomError::SwapAccountMismatch
+            );
+
+            let cpi_accounts = Transfer {
+                from: counterparty_nft_token_account.clone(),
+                to: initiator_nft_token_account.clone(),
+                authority: ctx.accounts.counterparty.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            transfer(cpi_ctx, 1)?; // Transfer 1 NFT
+        }
+
+        // Release the escrowed NFTs to the counterparty
+        let swap_key = swap.key();
+        let seeds = &[
+            b"escrow_authority",
+            swap_key.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+        release_escrowed_nfts(
+            &swap.offered_mints,
+            ctx.accounts.counterparty.key(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.initiator,
+            &ctx.accounts.token_program.to_account_info(),
+            offered_accounts,
+            signer,
+            CustomError::SwapAccountMismatch,
+        )?;
+
+        // The counterparty pays their top-up to the initiator, and the initiator's
+        // escrowed top-up is released to the counterparty
+        match swap.topup_mint {
+            Some(topup_mint) => {
+                let initiator_payment_token_account = ctx.accounts.initiator_payment_token_account.as_ref()
+                    .ok_or(CustomError::MissingPaymentAccount)?;
+                let counterparty_payment_token_account = ctx.accounts.counterparty_payment_token_account.as_ref()
+                    .ok_or(CustomError::MissingPaymentAccount)?;
+                let swap_vault = ctx.accounts.swap_vault.as_ref().ok_or(CustomError::MissingPaymentAccount)?;
+                let source = PaymentSource::Token {
+                    from: counterparty_payment_token_account.to_account_info(),
+                    authority: ctx.accounts.counterparty.to_account_info(),
+                    mint: topup_mint,
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                    signer_seeds: &[],
+                };
+                source.pay(&initiator_payment_token_account.to_account_info(), swap.counterparty_topup)?;
+                release_swap_vault(
+                    swap_vault,
+                    &counterparty_payment_token_account.to_account_info(),
+                    &ctx.accounts.escrow_authority.to_account_info(),
+                    &ctx.accounts.initiator,
+                    &ctx.accounts.token_program.to_account_info(),
+                    signer,
+                )?;
+            }
+            None => {
+                let source = PaymentSource::Wallet {
+                    payer: ctx.accounts.counterparty.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                };
+                source.pay(&ctx.accounts.initiator, swap.counterparty_topup)?;
+                let source = PaymentSource::Escrow {
+                    account: swap.to_account_info(),
+                };
+                source.pay(&ctx.accounts.counterparty.to_account_info(), swap.initiator_topup)?;
+            }
+        }
+
+        msg!("Swap {} accepted", swap.swap_id);
+        Ok(())
+    }
+
+    // Barter Program: reject_swap instruction
+    // Returns the escrowed NFTs and top-up to the initiator. Remaining accounts come in
+    // [escrow_nft_token_account, initiator_nft_token_account] pairs, in swap.offered_mints order.
+    pub fn reject_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RejectSwap<'info>>,
+    ) -> Result<()> {
+        let swap = &ctx.accounts.swap;
+        let swap_key = swap.key();
+        let seeds = &[
+            b"escrow_authority",
+            swap_key.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+        release_escrowed_nfts(
+            &swap.offered_mints,
+            swap.initiator,
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.initiator,
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.remaining_accounts,
+            signer,
+            CustomError::SwapAccountMismatch,
+        )?;
+
+        // SOL top-ups are refunded when the swap account is closed to the initiator
+        if swap.topup_mint.is_some() {
+            let initiator_payment_token_account = ctx.accounts.initiator_payment_token_account.as_ref()
+                .ok_or(CustomError::MissingPaymentAccount)?;
+            let swap_vault = ctx.accounts.swap_vault.as_ref().ok_or(CustomError::MissingPaymentAccount)?;
+            release_swap_vault(
+                swap_vault,
+                &initiator_payment_token_account.to_account_info(),
+                &ctx.accounts.escrow_authority.to_account_info(),
+                &ctx.accounts.initiator,
+                &ctx.accounts.token_program.to_account_info(),
+                signer,
+            )?;
+        }
+
+        msg!("Swap {} rejected", swap.swap_id);
         Ok(())
     }
+
+    // Barter Program: cancel_swap instruction
+    // Remaining accounts come in [escrow_nft_token_account, initiator_nft_token_account]
+    // pairs, in swap.offered_mints order
+    pub fn cancel_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelSwap<'info>>,
+    ) -> Result<()> {
+        let swap = &ctx.accounts.swap;
+        let swap_key = swap.key();
+        let seeds = &[
+            b"escrow_authority",
+            swap_key.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+        release_escrowed_nfts(
+            &swap.offered_mints,
+            swap.initiator,
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.initiator.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.remaining_accounts,
+            signer,
+            CustomError::SwapAccountMismatch,
+        )?;
+
+        // SOL top-ups are refunded when the swap account is closed to the initiator
+        if swap.topup_mint.is_some() {
+            let initiator_payment_token_account = ctx.accounts.initiator_payment_token_account.as_ref()
+                .ok_or(CustomError::MissingPaymentAccount)?;
+            let swap_vault = ctx.accounts.swap_vault.as_ref().ok_or(CustomError::MissingPaymentAccount)?;
+            release_swap_vault(
+                swap_vault,
+                &initiator_payment_token_account.to_account_info(),
+                &ctx.accounts.escrow_authority.to_account_info(),
+                &ctx.accounts.initiator.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                signer,
+            )?;
+        }
+
+        msg!("Swap {} cancelled", swap.swap_id);
+        Ok(())
+    }
+
+    // Intent Pool Program: post_buy_intent instruction
+    // Escrows max_price in SOL for a specific NFT mint or any verified NFT of a collection.
+    // max_price also covers the crank fee paid to whoever matches the intent. The bounty
+    // goes to whoever sweeps the intent once it expires.
+    pub fn post_buy_intent(
+        ctx: Context<PostBuyIntent>,
+        intent_id: u64,
+        nft_mint: Option<Pubkey>,
+        collection: Option<Pubkey>,
+        max_price: u64,
+        expires_at: i64,
+        bounty: u64, // In lamports
+    ) -> Result<()> {
+        require!(nft_mint.is_some() != collection.is_some(), CustomError::InvalidIntentTarget);
+        require!(expires_at > Clock::get()?.unix_timestamp, CustomError::InvalidIntentExpiry);
+
+        // Escrow the SOL and the bounty in the intent account
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.buyer.to_account_info(),
+            to: ctx.accounts.buy_intent.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.system_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        system_program::transfer(cpi_ctx, max_price.checked_add(bounty).unwrap())?;
+
+        let buy_intent = &mut ctx.accounts.buy_intent;
+        buy_intent.buyer = ctx.accounts.buyer.key();
+        buy_intent.intent_id = intent_id;
+        buy_intent.nft_mint = nft_mint;
+        buy_intent.collection = collection;
+        buy_intent.max_price = max_price;
+        buy_intent.expires_at = expires_at;
+        buy_intent.bounty = bounty;
+        buy_intent.bump = ctx.bumps.buy_intent;
+
+        msg!("Buy intent {} posted with max price: {}", intent_id, max_price);
+        Ok(())
+    }
+
+    // Intent Pool Program: cancel_buy_intent instruction
+    pub fn cancel_buy_intent(
+        ctx: Context<CancelBuyIntent>,
+    ) -> Result<()> {
+        // The escrowed SOL and bounty are refunded when the intent account is closed to the buyer
+        msg!("Buy intent {} cancelled", ctx.accounts.buy_intent.intent_id);
+        Ok(())
+    }
+
+    // Intent Pool Program: post_sell_intent instruction
+    // Escrows the NFT for sale to any matching buy intent at min_price or more. The bounty
+    // goes to whoever sweeps the intent once it expires.
+    pub fn post_sell_intent(
+        ctx: Context<PostSellIntent>,
+        min_price: u64,
+        expires_at: i64,
+        bounty: u64, // In lamports
+    ) -> Result<()> {
+        require!(expires_at > Clock::get()?.unix_timestamp, CustomError::InvalidIntentExpiry);
+
+        // Escrow the bounty in the intent account
+        if bounty > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.seller.to_account_info(),
+                to: ctx.accounts.sell_intent.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.system_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            system_program::transfer(cpi_ctx, bounty)?;
+        }
+
+        // Transfer NFT from seller to escrow account
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.seller_nft_token_account.to_account_info(),
+            to: ctx.accounts.escrow_nft_token_account.to_account_info(),
+            authority: ctx.accounts.seller.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer(cpi_ctx, 1)?; // Transfer 1 NFT
+
+        let sell_intent = &mut ctx.accounts.sell_intent;
+        sell_intent.seller = ctx.accounts.seller.key();
+        sell_intent.nft_mint = ctx.accounts.nft_mint.key();
+        sell_intent.min_price = min_price;
+        sell_intent.expires_at = expires_at;
+        sell_intent.bounty = bounty;
+        sell_intent.escrow_nft_token_account = ctx.accounts.escrow_nft_token_account.key();
+        sell_intent.escrow_authority = ctx.accounts.escrow_authority.key();
+        sell_intent.bump = ctx.bumps.sell_intent;
+        ctx.accounts.escrow_authority.bump = ctx.bumps.escrow_authority;
+
+        msg!("Sell intent posted for NFT {} with min price: {}", sell_intent.nft_mint, min_price);
+        Ok(())
+    }
+
+    // Intent Pool Program: cancel_sell_intent instruction
+    pub fn cancel_sell_intent(
+        ctx: Context<CancelSellIntent>,
+    ) -> Result<()> {
+        let sell_intent_key = ctx.accounts.sell_intent.key();
+        let seeds = &[
+            b"escrow_authority",
+            sell_intent_key.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+
+        // Return the NFT from escrow to the seller
+        release_escrowed_nft(
+            &ctx.accounts.escrow_nft_token_account.to_account_info(),
+            &ctx.accounts.seller_nft_token_account.to_account_info(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            signer,
+        )?;
+
+        msg!("Sell intent cancelled for NFT: {}", ctx.accounts.sell_intent.nft_mint);
+        Ok(())
+    }
+
+    // Intent Pool Program: match_intents instruction
+    // Permissionless: settles a compatible buy and sell intent at the seller's min_price.
+    // The cranker earns MATCH_CRANK_FEE_BASIS_POINTS of the price from the buyer's escrow
+    // and the buyer gets the rest of the escrow back. Bounties are refunded to their owners.
+    // Creator accounts are passed as remaining accounts, in nft_metadata.creators order.
+    pub fn match_intents<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MatchIntents<'info>>,
+    ) -> Result<()> {
+        let buy_intent = &ctx.accounts.buy_intent;
+        let sell_intent = &ctx.accounts.sell_intent;
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            current_time < buy_intent.expires_at && current_time < sell_intent.expires_at,
+            CustomError::IntentExpired
+        );
+        match (buy_intent.nft_mint, buy_intent.collection) {
+            (Some(nft_mint), _) => {
+                require_keys_eq!(nft_mint, sell_intent.nft_mint, CustomError::IntentTargetMismatch);
+            }
+            (None, collection) => {
+                require!(ctx.accounts.nft_metadata.collection == collection, CustomError::IntentTargetMismatch);
+                require!(ctx.accounts.nft_metadata.collection_verified, CustomError::CollectionNotVerified);
+            }
+        }
+        let price = sell_intent.min_price;
+        let crank_fee = platform_fee(price, MATCH_CRANK_FEE_BASIS_POINTS);
+        require!(
+            price.checked_add(crank_fee).is_some_and(|total| total <= buy_intent.max_price),
+            CustomError::IntentPriceMismatch
+        );
+
+        // Transfer NFT from escrow to buyer
+        let sell_intent_key = sell_intent.key();
+        let seeds = &[
+            b"escrow_authority",
+            sell_intent_key.as_ref(),
+            &[ctx.accounts.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+
+        release_escrowed_nft(
+            &ctx.accounts.escrow_nft_token_account.to_account_info(),
+            &ctx.accounts.buyer_nft_token_account.to_account_info(),
+            &ctx.accounts.escrow_authority.to_account_info(),
+            &ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            signer,
+        )?;
+
+        // Pay the sale and the crank fee out of the buyer's escrow. The remainder is
+        // refunded when the buy intent is closed to the buyer.
+        let source = PaymentSource::Escrow {
+            account: buy_intent.to_account_info(),
+        };
+        let (fee, total_royalty) = settle_sale(
+            &source,
+            price,
+            ctx.accounts.marketplace_config.fee_basis_points,
+            &ctx.accounts.treasury,
+            &ctx.accounts.nft_metadata,
+            ctx.remaining_accounts,
+            &ctx.accounts.seller,
+        )?;
+        source.pay(&ctx.accounts.cranker.to_account_info(), crank_fee)?;
+
+        msg!(
+            "Intents matched at {}, platform fee: {}, royalties: {}, crank fee: {}",
+            price, fee, total_royalty, crank_fee
+        );
+        Ok(())
+    }
+
+    // Intent Pool Program: sweep_expired_intents instruction
+    // Permissionless: closes expired intents, refunds the escrowed SOL or NFT to the owner
+    // and pays each intent's bounty to the cranker. Remaining accounts come in
+    // [buy_intent, buyer] groups for buy intents and [sell_intent, seller, escrow_authority,
+    // escrow_nft_token_account, seller_nft_token_account] groups for sell intents.
+    pub fn sweep_expired_intents<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepExpiredIntents<'info>>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let cranker = ctx.accounts.cranker.to_account_info();
+
+        let mut remaining_accounts = ctx.remaining_accounts;
+        let mut swept: u32 = 0;
+        let mut total_bounty: u64 = 0;
+        while !remaining_accounts.is_empty() {
+            if let Ok(buy_intent) = Account::<BuyIntent>::try_from(&remaining_accounts[0]) {
+                require!(remaining_accounts.len() >= 2, CustomError::InvalidIntentAccounts);
+                let (group, rest) = remaining_accounts.split_at(2);
+                remaining_accounts = rest;
+                let buyer = &group[1];
+                require_keys_eq!(buyer.key(), buy_intent.buyer, CustomError::InvalidIntentAccounts);
+                require!(current_time >= buy_intent.expires_at, CustomError::IntentNotExpired);
+
+                // Pay the bounty, then refund the escrowed SOL and rent to the buyer
+                let source = PaymentSource::Escrow {
+                    account: buy_intent.to_account_info(),
+                };
+                source.pay(&cranker, buy_intent.bounty)?;
+                total_bounty = total_bounty.checked_add(buy_intent.bounty).unwrap();
+                buy_intent.close(buyer.clone())?;
+            } else {
+                let sell_intent = Account::<SellIntent>::try_from(&remaining_accounts[0])
+                    .map_err(|_| CustomError::InvalidIntentAccounts)?;
+                require!(remaining_accounts.len() >= 5, CustomError::InvalidIntentAccounts);
+                let (group, rest) = remaining_accounts.split_at(5);
+                remaining_accounts = rest;
+                let (seller, escrow_nft_token_account, seller_nft_token_account) = (&group[1], &group[3], &group[4]);
+                require_keys_eq!(seller.key(), sell_intent.seller, CustomError::InvalidIntentAccounts);
+                require_keys_eq!(group[2].key(), sell_intent.escrow_authority, CustomError::InvalidIntentAccounts);
+                require_keys_eq!(
+                    escrow_nft_token_account.key(),
+                    sell_intent.escrow_nft_token_account,
+                    CustomError::InvalidIntentAccounts
+                );
+                require_keys_eq!(
+                    seller_nft_token_account.key(),
+                    get_associated_token_address(&sell_intent.seller, &sell_intent.nft_mint),
+                    CustomError::InvalidIntentAccounts
+                );
+                require!(current_time >= sell_intent.expires_at, CustomError::IntentNotExpired);
+                let escrow_authority = Account::<EscrowAuthority>::try_from(&group[2])?;
+
+                let sell_intent_key = sell_intent.key();
+                let seeds = &[
+                    b"escrow_authority",
+                    sell_intent_key.as_ref(),
+                    &[escrow_authority.bump]
+                ];
+                let signer = &[&seeds[..]];
+
+                // Return the NFT from escrow to the seller
+                release_escrowed_nft(
+                    escrow_nft_token_account,
+                    seller_nft_token_account,
+                    &escrow_authority.to_account_info(),
+                    seller,
+                    &ctx.accounts.token_program.to_account_info(),
+                    signer,
+                )?;
+
+                // Pay the bounty, then refund the rent to the seller
+                let source = PaymentSource::Escrow {
+                    account: sell_intent.to_account_info(),
+                };
+                source.pay(&cranker, sell_intent.bounty)?;
+                total_bounty = total_bounty.checked_add(sell_intent.bounty).unwrap();
+                sell_intent.close(seller.clone())?;
+                escrow_authority.close(seller.clone())?;
+            }
+            swept += 1;
+        }
+
+        msg!("Swept {} expired intents, bounties paid: {}", swept, total_bounty);
+        Ok(())
+    }
+
+    // Referral Program: create_referral_code instruction
+    pub fn create_referral_code(
+        ctx: Context<CreateReferralCode>,
+        code: String,
+    ) -> Result<()> {
+        require!(
+            !code.is_empty() && code.len() <= MAX_REFERRAL_CODE_LEN && code.bytes().all(|byte| byte.is_ascii_alphanumeric()),
+            CustomError::InvalidReferralCode
+        );
+
+        let referral_code = &mut ctx.accounts.referral_code;
+        referral_code.referrer = ctx.accounts.referrer.key();
+        referral_code.code = code;
+        referral_code.claimable = 0;
+        referral_code.bump = ctx.bumps.referral_code;
+
+        msg!("Referral code {} created", referral_code.code);
+        Ok(())
+    }
+
+    // Referral Program: register_referral instruction
+    // Binds the user to the owner of a referral code. A user can only bind once.
+    pub fn register_referral(
+        ctx: Context<RegisterReferral>,
+        code: String,
+    ) -> Result<()> {
+        require_keys_neq!(
+            ctx.accounts.referral_code.referrer,
+            ctx.accounts.user.key(),
+            CustomError::SelfReferral
+        );
+
+        let referral = &mut ctx.accounts.referral;
+        referral.user = ctx.accounts.user.key();
+        referral.referral_code = ctx.accounts.referral_code.key();
+        referral.bump = ctx.bumps.referral;
+
+        msg!("User {} registered with referral code {}", referral.user, code);
+        Ok(())
+    }
+
+    // Referral Program: claim_referral_rewards instruction
+    // Pays the accrued CFISH commission out of the referral vault. Commissions are not
+    // backed by the SOL fees, so the vault is topped up with CFISH by the marketplace.
+    // When it holds less than the accrued amount, its balance is paid out and the rest
+    // stays claimable.
+    pub fn claim_referral_rewards(
+        ctx: Context<ClaimReferralRewards>,
+    ) -> Result<()> {
+        let claimable = ctx.accounts.referral_code.claimable;
+        require!(claimable > 0, CustomError::NothingToClaim);
+        let amount = claimable.min(ctx.accounts.referral_vault.amount);
+        require!(amount > 0, CustomError::ReferralVaultEmpty);
+
+        let seeds = &[
+            b"marketplace_config".as_ref(),
+            &[ctx.accounts.marketplace_config.bump]
+        ];
+        let signer = &[&seeds[..]];
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.referral_vault.to_account_info(),
+            to: ctx.accounts.referrer_cfish_token_account.to_account_info(),
+            authority: ctx.accounts.marketplace_config.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        transfer(cpi_ctx, amount)?;
+
+        ctx.accounts.referral_code.claimable = claimable - amount;
+
+        msg!("Referral rewards claimed: {} CFISH, {} left to claim", amount, claimable - amount);
+        Ok(())
+    }
+
+    // Staking & Governance Program: stake instruction
+    pub fn stake(
+        ctx: Context<Stake>,
+        amount: u64,
+        duration_days: u64,
+    ) -> Result<()> {
+        // Transfer CFISH tokens from staker to stake account
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.staker_token_account.to_account_info(),
+            to: ctx.accounts.stake_account.to_account_info(),
+            authority: ctx.accounts.staker.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        transfer(cpi_ctx, amount)?;
+
+        // Update stake entry
+        ctx.accounts.stake_entry.staker = ctx.accounts.staker.key();
+        ctx.accounts.stake_entry.amount = ctx.accounts.stake_entry.amount.checked_add(amount).unwrap();
+        ctx.accounts.stake_entry.stake_start_time = Clock::get()?.unix_timestamp;
+        ctx.accounts.stake_entry.duration_days = duration_days;
+        ctx.accounts.stake_entry.claimed_rewards = 0;
+
+        msg!("Staked {} CFISH tokens for {} days", amount, duration_days);
+        Ok(())
+    }
+
+    // Staking & Governance Program: unstake instruction
+    pub fn unstake(
+        ctx: Context<Unstake>,
+    ) -> Result<()> {
+        // Calculate rewards (simplified calculation)
+        let current_time = Clock::get()?.unix_timestamp;
+        let elapsed_days = (current_time - ctx.accounts.stake_entry.stake_start_time) / (24 * 60 * 60);
+        let rewards = (ctx.accounts.stake_entry.amount as f64 * 0.01 * elapsed_days as f64) as u64; // 1% daily reward
+
+        // Transfer CFISH tokens + rewards from stake account to staker
+        let staker_key = ctx.accounts.staker.key();
+        let seeds = &[
+            b"stake_authority",
+            staker_key.as_ref(),
+            &[ctx.accounts.stake_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+        
+        let total_amount = ctx.accounts.stake_entry.amount.checked_add(rewards).unwrap();
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.stake_account.to_account_info(),
+            to: ctx.accounts.staker_token_account.to_account_info(),
+            authority: ctx.accounts.stake_authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        transfer(cpi_ctx, total_amount)?;
+
+        // Reset stake entry
+        ctx.accounts.stake_entry.amount = 0;
+        ctx.accounts.stake_entry.claimed_rewards = ctx.accounts.stake_entry.claimed_rewards.checked_add(rewards).unwrap();
+
+        msg!("Unstaked {} CFISH tokens with {} rewards", ctx.accounts.stake_entry.amount, rewards);
+        Ok(())
+    }
+
+    // Trading Mining: distribute_reward instruction
+    pub fn distribute_reward(
+        ctx: Context<DistributeReward>,
+        reward_amount: u64,
+    ) -> Result<()> {
+        // Check daily limit (simplified)
+        let current_day = Clock::get()?.unix_timestamp / (24 * 60 * 60);
+        if ctx.accounts.reward_tracker.last_reward_day == current_day {
+            ctx.accounts.reward_tracker.daily_count += 1;
+            require!(ctx.accounts.reward_tracker.daily_count <= 5, CustomError::DailyLimitExceeded);
+        } else {
+            ctx.accounts.reward_tracker.last_reward_day = current_day;
+            ctx.accounts.reward_tracker.daily_count = 1;
+        }
+
+        // Create vesting entry for reward
+        ctx.accounts.vesting_entry.beneficiary = ctx.accounts.user.key();
+        ctx.accounts.vesting_entry.total_amount = reward_amount;
+        ctx.accounts.vesting_entry.released_amount = 0;
+        ctx.accounts.vesting_entry.start_time = Clock::get()?.unix_timestamp + (24 * 60 * 60); // Start tomorrow
+        ctx.accounts.vesting_entry.duration = 180 * 24 * 60 * 60; // 180 days
+
+        msg!("Reward of {} CFISH distributed to user", reward_amount);
+        Ok(())
+    }
+
+    // Governance: create_proposal instruction
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        title: String,
+        description: String,
+        voting_period: i64,
+    ) -> Result<()> {
+        ctx.accounts.proposal.proposer = ctx.accounts.proposer.key();
+        ctx.accounts.proposal.title = title;
+        ctx.accounts.proposal.description = description;
+        ctx.accounts.proposal.start_time = Clock::get()?.unix_timestamp;
+        ctx.accounts.proposal.end_time = Clock::get()?.unix_timestamp + voting_period;
+        ctx.accounts.proposal.yes_votes = 0;
+        ctx.accounts.proposal.no_votes = 0;
+        ctx.accounts.proposal.executed = false;
+        ctx.accounts.proposal.bump = ctx.bumps.proposal;
+        msg!("Proposal created: {}", ctx.accounts.proposal.title);
+        Ok(())
+    }
+
+    // Governance: vote instruction
+    pub fn vote(
+        ctx: Context<Vote>,
+        vote_yes: bool,
+    ) -> Result<()> {
+        let voting_power = ctx.accounts.stake_entry.amount; // Voting power based on staked amount
+        
+        if vote_yes {
+            ctx.accounts.proposal.yes_votes = ctx.accounts.proposal.yes_votes.checked_add(voting_power).unwrap();
+        } else {
+            ctx.accounts.proposal.no_votes = ctx.accounts.proposal.no_votes.checked_add(voting_power).unwrap();
+        }
+
+        ctx.accounts.vote_record.voter = ctx.accounts.voter.key();
+        ctx.accounts.vote_record.proposal = ctx.accounts.proposal.key();
+        ctx.accounts.vote_record.vote_yes = vote_yes;
+        ctx.accounts.vote_record.voting_power = voting_power;
+
+        msg!("Vote cast: {} with power {}", if vote_yes { "YES" } else { "NO" }, voting_power);
+        Ok(())
+    }
+
+    // Tokenomics: release_vested_reward instruction
+    pub fn release_vested_reward(
+        ctx: Context<ReleaseVestedReward>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let vesting_entry = &mut ctx.accounts.vesting_entry;
+
+        require!(current_time >= vesting_entry.start_time, CustomError::VestingNotStarted);
+
+        let total_duration = vesting_entry.duration;
+        let elapsed_duration = current_time - vesting_entry.start_time;
+
+        let vested_amount = (vesting_entry.total_amount as f64 * (elapsed_duration as f64 / total_duration as f64)) as u64;
+        let amount_to_release = vested_amount.checked_sub(vesting_entry.released_amount).unwrap();
+
+        require!(amount_to_release > 0, CustomError::NoRewardsToRelease);
+
+        // Transfer vested tokens from authority to beneficiary
+        let authority_key = ctx.accounts.authority.key();
+        let seeds = &[
+            b"authority",
+            authority_key.as_ref(),
+            &[ctx.accounts.authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.authority_token_account.to_account_info(),
+            to: ctx.accounts.beneficiary_token_account.to_account_info(),
+            authority: ctx.accounts.authority.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        transfer(cpi_ctx, amount_to_release)?;
+
+        vesting_entry.released_amount = vesting_entry.released_amount.checked_add(amount_to_release).unwrap();
+
+        msg!("Released {} vested CFISH tokens", amount_to_release);
+        Ok(())
+    }
+}
+
+// Account structures for mint_nft instruction
+#[derive(Accounts)]
+pub struct MintNft<
+    'info
+> {
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 0,
+        mint::authority = mint_authority
+    )]
+    pub mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub mint_authority: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = mint_authority
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 32 + 32 + 4 + 100 + 4 + 10 + 4 + 200 + 2 + 4 + MAX_CREATORS * (32 + 1) + 1 + 32 + 1, // Discriminator + mint + creator + name + symbol + uri + seller_fee_basis_points + creators + collection + collection_verified
+        seeds = [b"nft_metadata", mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// Account structures for mint_nft_metaplex instruction
+#[derive(Accounts)]
+pub struct MintNftMetaplex<'info> {
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 0,
+        mint::authority = mint_authority,
+        mint::freeze_authority = mint_authority
+    )]
+    pub mint: Account<'info, Mint>,
+    #[account(mut)]
+    pub mint_authority: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = mint_authority
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Initialized by the Token Metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub metadata: AccountInfo<'info>,
+    /// CHECK: Initialized by the Token Metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub master_edition: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 32 + 32 + 4 + 100 + 4 + 10 + 4 + 200 + 2 + 4 + MAX_CREATORS * (32 + 1) + 1 + 32 + 1, // Discriminator + mint + creator + name + symbol + uri + seller_fee_basis_points + creators + collection + collection_verified
+        seeds = [b"nft_metadata", mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// Account structures for create_master_edition instruction
+#[derive(Accounts)]
+pub struct CreateMasterEdition<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        has_one = mint,
+        has_one = creator,
+        seeds = [b"nft_metadata", mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    /// CHECK: Must be empty, a print cannot become a master edition itself
+    #[account(
+        seeds = [b"edition", mint.key().as_ref()],
+        bump,
+        constraint = print_edition_record.data_is_empty() @ CustomError::EditionIsPrint
+    )]
+    pub print_edition_record: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + 32 + 8 + 8 + 1, // Discriminator + mint + max_supply + supply + bump
+        seeds = [b"master_edition", mint.key().as_ref()],
+        bump
+    )]
+    pub master_edition: Account<'info, MasterEdition>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for print_edition instruction
+#[derive(Accounts)]
+pub struct PrintEdition<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub master_mint: Account<'info, Mint>,
+    #[account(
+        constraint = master_nft_metadata.creator == creator.key(),
+        seeds = [b"nft_metadata", master_mint.key().as_ref()],
+        bump
+    )]
+    pub master_nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        mut,
+        seeds = [b"master_edition", master_mint.key().as_ref()],
+        bump = master_edition.bump
+    )]
+    pub master_edition: Account<'info, MasterEdition>,
+    #[account(
+        init,
+        payer = creator,
+        mint::decimals = 0,
+        mint::authority = creator
+    )]
+    pub edition_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = creator,
+        associated_token::mint = edition_mint,
+        associated_token::authority = creator
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + 32 + 32 + 4 + 100 + 4 + 10 + 4 + 200 + 2 + 4 + MAX_CREATORS * (32 + 1) + 1 + 32 + 1, // Discriminator + mint + creator + name + symbol + uri + seller_fee_basis_points + creators + collection + collection_verified
+        seeds = [b"nft_metadata", edition_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + 32 + 8, // Discriminator + parent + edition
+        seeds = [b"edition", edition_mint.key().as_ref()],
+        bump
+    )]
+    pub edition: Account<'info, Edition>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// Account structures for verify_collection instruction
+#[derive(Accounts)]
+pub struct VerifyCollection<'info> {
+    pub collection_authority: Signer<'info>,
+    pub collection_mint: Account<'info, Mint>,
+    #[account(
+        constraint = collection_metadata.creator == collection_authority.key() @ CustomError::CollectionMismatch,
+        seeds = [b"nft_metadata", collection_mint.key().as_ref()],
+        bump
+    )]
+    pub collection_metadata: Account<'info, NftMetadata>,
+    #[account(
+        mut,
+        constraint = nft_metadata.collection == Some(collection_mint.key()) @ CustomError::CollectionMismatch,
+        seeds = [b"nft_metadata", nft_metadata.mint.as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+}
+
+// Account structures for initialize_marketplace_config instruction
+#[derive(Accounts)]
+pub struct InitializeMarketplaceConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::CfishContract>,
+    // Only the upgrade authority may create the global config
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ CustomError::NotUpgradeAuthority)]
+    pub program_data: Account<'info, ProgramData>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 2 + 32 + 32 + 2 + 8 + 1, // Discriminator + authority + fee_basis_points + treasury + cfish_mint + referral_share_bps + cfish_per_sol + bump
+        seeds = [b"marketplace_config"],
+        bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for update_marketplace_config instruction
+#[derive(Accounts)]
+pub struct UpdateMarketplaceConfig<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+}
+
+// Account structures for update_referral_config instruction
+#[derive(Accounts)]
+pub struct UpdateReferralConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    #[account(address = marketplace_config.cfish_mint @ CustomError::UnsupportedPaymentMint)]
+    pub cfish_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        token::mint = cfish_mint,
+        token::authority = marketplace_config,
+        seeds = [b"referral_vault"],
+        bump
+    )]
+    pub referral_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for list_nft instruction
+#[derive(Accounts)]
+#[instruction(price: u64)]
+pub struct ListNft<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut)]
+    pub nft_mint: Account<'info, Mint>,
+    // Required so the sale can pay royalties; only NFTs minted by this program can be sold
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        token::mint = nft_mint,
+        token::authority = escrow_authority,
+        seeds = [b"escrow", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + 32 + 32 + 8 + 1 + 32 + 32 + 32 + 1 + 8 + 1 + 32 + 1, // Discriminator + seller + nft_mint + price + payment_mint + escrow_nft_token_account + escrow_authority + expires_at + reserved_buyer + delegated
+        seeds = [b"listing", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    /// CHECK: This is the PDA authority for the escrow account
+    #[account(
+        init,
+        payer = seller,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump,
+        space = 8 + 1 // Discriminator + bump
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+}
+
+// Account structures for list_nft_delegated instruction
+#[derive(Accounts)]
+pub struct ListNftDelegated<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub nft_mint: Account<'info, Mint>,
+    // Required so the sale can pay royalties; only NFTs minted by this program can be sold
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller,
+        constraint = seller_nft_token_account.amount == 1 @ CustomError::NftNotHeld
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + 32 + 32 + 8 + 1 + 32 + 32 + 32 + 1 + 8 + 1 + 32 + 1, // Discriminator + seller + nft_mint + price + payment_mint + escrow_nft_token_account + escrow_authority + expires_at + reserved_buyer + delegated
+        seeds = [b"listing", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    #[account(
+        init,
+        payer = seller,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump,
+        space = 8 + 1 // Discriminator + bump
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for buy_nft instruction
+#[derive(Accounts)]
+pub struct BuyNft<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match listing.seller, enforced by the has_one constraint on the listing
+    pub seller: AccountInfo<'info>,
+    #[account(mut,
+        constraint = listing.nft_mint == nft_mint.key(),
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = nft_mint,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        close = seller
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(mut)]
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    /// CHECK: Receives the platform fee, validated against the marketplace config
+    #[account(mut, address = marketplace_config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = buyer
+    )]
+    pub buyer_nft_token_account: Account<'info, TokenAccount>,
+    // The escrow PDA, or the seller's own token account for delegated listings.
+    // Bound to the listing by its has_one constraint.
+    #[account(mut,
+        token::mint = nft_mint
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    // Only required for listings priced in CFISH
+    #[account(
+        mut,
+        token::mint = marketplace_config.cfish_mint,
+        token::authority = buyer
+    )]
+    pub buyer_payment_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = marketplace_config.cfish_mint,
+        token::authority = seller
+    )]
+    pub seller_payment_token_account: Option<Account<'info, TokenAccount>>,
+    /// CHECK: The buyer's referral PDA, which only exists once they registered a referral.
+    /// Deserialized in accrue_referral.
+    #[account(
+        seeds = [b"referral", buyer.key().as_ref()],
+        bump
+    )]
+    pub referral: AccountInfo<'info>,
+    // Required when the buyer registered a referral
+    #[account(mut)]
+    pub referral_code: Option<Account<'info, ReferralCode>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// Account structures for buy_many instruction
+#[derive(Accounts)]
+pub struct BuyMany<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    /// CHECK: Receives the platform fee, validated against the marketplace config
+    #[account(mut, address = marketplace_config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    // Only required for sweeps priced in CFISH
+    #[account(
+        mut,
+        token::mint = marketplace_config.cfish_mint,
+        token::authority = buyer
+    )]
+    pub buyer_payment_token_account: Option<Account<'info, TokenAccount>>,
+    /// CHECK: The buyer's referral PDA, which only exists once they registered a referral.
+    /// Deserialized in accrue_referral.
+    #[account(
+        seeds = [b"referral", buyer.key().as_ref()],
+        bump
+    )]
+    pub referral: AccountInfo<'info>,
+    // Required when the buyer registered a referral
+    #[account(mut)]
+    pub referral_code: Option<Account<'info, ReferralCode>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for cancel_listing instruction
+#[derive(Accounts)]
+pub struct CancelListing<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut,
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = nft_mint,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        seeds = [b"listing", nft_mint.key().as_ref()],
+        bump,
+        close = seller
+    )]
+    pub listing: Account<'info, Listing>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    // The escrow PDA, or the seller's own token account for delegated listings.
+    // Bound to the listing by its has_one constraint.
+    #[account(mut,
+        token::mint = nft_mint
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for expire_listing instruction
+#[derive(Accounts)]
+pub struct ExpireListing<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match listing.seller, enforced by the has_one constraint on the listing
+    pub seller: AccountInfo<'info>,
+    #[account(mut,
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = nft_mint,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        seeds = [b"listing", nft_mint.key().as_ref()],
+        bump,
+        close = seller
+    )]
+    pub listing: Account<'info, Listing>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    // The escrow PDA, or the seller's own token account for delegated listings.
+    // Bound to the listing by its has_one constraint.
+    #[account(mut,
+        token::mint = nft_mint
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for close_revoked_listing instruction
+#[derive(Accounts)]
+pub struct CloseRevokedListing<'info> {
+    pub cranker: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match listing.seller, enforced by the has_one constraint on the listing
+    pub seller: AccountInfo<'info>,
+    #[account(mut,
+        constraint = listing.delegated @ CustomError::ListingNotDelegated,
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        seeds = [b"listing", listing.nft_mint.as_ref()],
+        bump,
+        close = seller
+    )]
+    pub listing: Account<'info, Listing>,
+    /// CHECK: The seller's token account the NFT was listed from, bound to the listing by its
+    /// has_one constraint. Deserialized in the handler since the seller may have closed it.
+    pub escrow_nft_token_account: AccountInfo<'info>,
+    #[account(mut,
+        seeds = [b"escrow_authority", listing.nft_mint.as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+}
+
+// Account structures for update_listing_price instruction
+#[derive(Accounts)]
+pub struct UpdateListingPrice<'info> {
+    pub seller: Signer<'info>,
+    #[account(mut,
+        has_one = seller @ CustomError::SellerMismatch,
+        seeds = [b"listing", listing.nft_mint.as_ref()],
+        bump
+    )]
+    pub listing: Account<'info, Listing>,
+}
+
+// Account structures for create_auction instruction
+#[derive(Accounts)]
+pub struct CreateAuction<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub nft_mint: Account<'info, Mint>,
+    // Required so the sale can pay royalties; only NFTs minted by this program can be sold
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        token::mint = nft_mint,
+        token::authority = escrow_authority,
+        seeds = [b"escrow", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump,
+        space = 8 + 1 // Discriminator + bump
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1 + 32 + 8 + 8 + 8 + 1, // Discriminator + seller + nft_mint + escrow_nft_token_account + escrow_authority + reserve_price + min_bid_increment + highest_bid + highest_bidder + start_time + end_time + extension_window + bump
+        seeds = [b"auction", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub auction: Account<'info, Auction>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// Account structures for place_bid instruction
+#[derive(Accounts)]
+pub struct PlaceBid<'info> {
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    #[account(mut,
+        seeds = [b"auction", auction.nft_mint.as_ref()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    /// CHECK: Must match auction.highest_bidder, checked in the instruction
+    #[account(mut)]
+    pub previous_bidder: Option<UncheckedAccount<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for settle_auction instruction
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match auction.seller, enforced by the has_one constraint on the auction
+    pub seller: AccountInfo<'info>,
+    /// CHECK: The winning bidder, or the seller if there were no bids. Checked in the instruction
+    pub recipient: AccountInfo<'info>,
+    #[account(mut,
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = nft_mint,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        seeds = [b"auction", nft_mint.key().as_ref()],
+        bump = auction.bump,
+        close = seller
+    )]
+    pub auction: Account<'info, Auction>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    /// CHECK: Receives the platform fee, validated against the marketplace config
+    #[account(mut, address = marketplace_config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = recipient
+    )]
+    pub recipient_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        token::mint = nft_mint,
+        token::authority = escrow_authority,
+        seeds = [b"escrow", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for cancel_auction instruction
+#[derive(Accounts)]
+pub struct CancelAuction<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut,
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = nft_mint,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        seeds = [b"auction", nft_mint.key().as_ref()],
+        bump = auction.bump,
+        close = seller
+    )]
+    pub auction: Account<'info, Auction>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        token::mint = nft_mint,
+        token::authority = escrow_authority,
+        seeds = [b"escrow", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for unwind_auction instruction
+#[derive(Accounts)]
+pub struct UnwindAuction<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match auction.seller, enforced by the has_one constraint on the auction
+    pub seller: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: Must match auction.highest_bidder, checked in the instruction
+    pub highest_bidder: AccountInfo<'info>,
+    #[account(mut,
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = nft_mint,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        seeds = [b"auction", nft_mint.key().as_ref()],
+        bump = auction.bump,
+        close = seller
+    )]
+    pub auction: Account<'info, Auction>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        token::mint = nft_mint,
+        token::authority = escrow_authority,
+        seeds = [b"escrow", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for list_dutch instruction
+#[derive(Accounts)]
+pub struct ListDutch<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub nft_mint: Account<'info, Mint>,
+    // Required so the sale can pay royalties; only NFTs minted by this program can be sold
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        token::mint = nft_mint,
+        token::authority = escrow_authority,
+        seeds = [b"escrow", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump,
+        space = 8 + 1 // Discriminator + bump
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1, // Discriminator + seller + nft_mint + escrow_nft_token_account + escrow_authority + start_price + floor_price + start_time + end_time + step_duration + bump
+        seeds = [b"dutch_listing", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub dutch_listing: Account<'info, DutchListing>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// Account structures for buy_dutch instruction
+#[derive(Accounts)]
+pub struct BuyDutch<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match dutch_listing.seller, enforced by the has_one constraint on the listing
+    pub seller: AccountInfo<'info>,
+    #[account(mut,
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = nft_mint,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        seeds = [b"dutch_listing", nft_mint.key().as_ref()],
+        bump = dutch_listing.bump,
+        close = seller
+    )]
+    pub dutch_listing: Account<'info, DutchListing>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    /// CHECK: Receives the platform fee, validated against the marketplace config
+    #[account(mut, address = marketplace_config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = buyer
+    )]
+    pub buyer_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        token::mint = nft_mint,
+        token::authority = escrow_authority,
+        seeds = [b"escrow", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for cancel_dutch instruction
+#[derive(Accounts)]
+pub struct CancelDutch<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut,
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = nft_mint,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        seeds = [b"dutch_listing", nft_mint.key().as_ref()],
+        bump = dutch_listing.bump,
+        close = seller
+    )]
+    pub dutch_listing: Account<'info, DutchListing>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        token::mint = nft_mint,
+        token::authority = escrow_authority,
+        seeds = [b"escrow", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(mut,
+        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for make_offer instruction
+#[derive(Accounts)]
+pub struct MakeOffer<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    pub nft_mint: Account<'info, Mint>,
+    // Required so the sale can pay royalties; only NFTs minted by this program can be sold
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + 32 + 32 + 8 + 1 + 32 + 1, // Discriminator + buyer + nft_mint + price + payment_mint + bump
+        seeds = [b"offer", nft_mint.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub offer: Account<'info, Offer>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    // Only required for offers in CFISH
+    #[account(address = marketplace_config.cfish_mint @ CustomError::UnsupportedPaymentMint)]
+    pub payment_mint: Option<Account<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = payment_mint,
+        token::authority = buyer
+    )]
+    pub buyer_payment_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = buyer,
+        token::mint = payment_mint,
+        token::authority = offer,
+        seeds = [b"offer_vault", offer.key().as_ref()],
+        bump
+    )]
+    pub offer_vault: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// Account structures for cancel_offer instruction
+#[derive(Accounts)]
+pub struct CancelOffer<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut,
+        has_one = buyer,
+        seeds = [b"offer", offer.nft_mint.as_ref(), buyer.key().as_ref()],
+        bump = offer.bump,
+        close = buyer
+    )]
+    pub offer: Account<'info, Offer>,
+    // Only required for offers in CFISH
+    #[account(
+        mut,
+        seeds = [b"offer_vault", offer.key().as_ref()],
+        bump
+    )]
+    pub offer_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::authority = buyer
+    )]
+    pub buyer_payment_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Account structures for accept_offer instruction
+#[derive(Accounts)]
+pub struct AcceptOffer<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match offer.buyer, enforced by the has_one constraint on the offer
+    pub buyer: AccountInfo<'info>,
+    #[account(mut,
+        has_one = buyer,
+        has_one = nft_mint,
+        seeds = [b"offer", nft_mint.key().as_ref(), buyer.key().as_ref()],
+        bump = offer.bump,
+        close = buyer
+    )]
+    pub offer: Account<'info, Offer>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    /// CHECK: Receives the platform fee, validated against the marketplace config
+    #[account(mut, address = marketplace_config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = nft_mint,
+        associated_token::authority = buyer
+    )]
+    pub buyer_nft_token_account: Account<'info, TokenAccount>,
+    // Only required for offers in CFISH
+    #[account(
+        mut,
+        seeds = [b"offer_vault", offer.key().as_ref()],
+        bump
+    )]
+    pub offer_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = marketplace_config.cfish_mint,
+        token::authority = seller
+    )]
+    pub seller_payment_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for make_collection_offer instruction
+#[derive(Accounts)]
+#[instruction(collection: Pubkey)]
+pub struct MakeCollectionOffer<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + 32 + 32 + 8 + 8 + 1, // Discriminator + buyer + collection + price + quantity + bump
+        seeds = [b"collection_offer", collection.as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub collection_offer: Account<'info, CollectionOffer>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for cancel_collection_offer instruction
+#[derive(Accounts)]
+pub struct CancelCollectionOffer<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut,
+        has_one = buyer,
+        seeds = [b"collection_offer", collection_offer.collection.as_ref(), buyer.key().as_ref()],
+        bump = collection_offer.bump,
+        close = buyer
+    )]
+    pub collection_offer: Account<'info, CollectionOffer>,
+}
+
+// Account structures for fill_collection_offer instruction
+#[derive(Accounts)]
+pub struct FillCollectionOffer<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match collection_offer.buyer, enforced by the has_one constraint on the offer
+    pub buyer: AccountInfo<'info>,
+    #[account(mut,
+        has_one = buyer,
+        seeds = [b"collection_offer", collection_offer.collection.as_ref(), buyer.key().as_ref()],
+        bump = collection_offer.bump
+    )]
+    pub collection_offer: Account<'info, CollectionOffer>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        constraint = nft_metadata.collection == Some(collection_offer.collection) @ CustomError::CollectionMismatch,
+        constraint = nft_metadata.collection_verified @ CustomError::CollectionNotVerified,
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    /// CHECK: Receives the platform fee, validated against the marketplace config
+    #[account(mut, address = marketplace_config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = nft_mint,
+        associated_token::authority = buyer
+    )]
+    pub buyer_nft_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for list_bundle instruction
+#[derive(Accounts)]
+#[instruction(bundle_id: u64)]
+pub struct ListBundle<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + 32 + 8 + 8 + 4 + MAX_BUNDLE_SIZE * 32 + 32 + 1, // Discriminator + seller + bundle_id + price + nft_mints + escrow_authority + bump
+        seeds = [b"bundle", seller.key().as_ref(), bundle_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub bundle: Account<'info, BundleListing>,
+    #[account(
+        init,
+        payer = seller,
+        seeds = [b"escrow_authority", bundle.key().as_ref()],
+        bump,
+        space = 8 + 1 // Discriminator + bump
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for buy_bundle instruction
+#[derive(Accounts)]
+pub struct BuyBundle<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match bundle.seller, enforced by the has_one constraint on the bundle
+    pub seller: AccountInfo<'info>,
+    #[account(mut,
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = escrow_authority,
+        seeds = [b"bundle", seller.key().as_ref(), bundle.bundle_id.to_le_bytes().as_ref()],
+        bump = bundle.bump,
+        close = seller
+    )]
+    pub bundle: Account<'info, BundleListing>,
+    #[account(mut,
+        seeds = [b"escrow_authority", bundle.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    /// CHECK: Receives the platform fee, validated against the marketplace config
+    #[account(mut, address = marketplace_config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for cancel_bundle instruction
+#[derive(Accounts)]
+pub struct CancelBundle<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut,
+        has_one = seller @ CustomError::SellerMismatch,
+        has_one = escrow_authority,
+        seeds = [b"bundle", seller.key().as_ref(), bundle.bundle_id.to_le_bytes().as_ref()],
+        bump = bundle.bump,
+        close = seller
+    )]
+    pub bundle: Account<'info, BundleListing>,
+    #[account(mut,
+        seeds = [b"escrow_authority", bundle.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Account structures for create_swap instruction
+#[derive(Accounts)]
+#[instruction(swap_id: u64)]
+pub struct CreateSwap<'info> {
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+    #[account(
+        init,
+        payer = initiator,
+        space = 8 + 32 + 32 + 8 + 4 + MAX_SWAP_SIZE * 32 + 4 + MAX_SWAP_SIZE * 32 + 1 + 32 + 8 + 8 + 32 + 1, // Discriminator + initiator + counterparty + swap_id + offered_mints + requested_mints + topup_mint + initiator_topup + counterparty_topup + escrow_authority + bump
+        seeds = [b"swap", initiator.key().as_ref(), swap_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub swap: Account<'info, SwapProposal>,
+    #[account(
+        init,
+        payer = initiator,
+        seeds = [b"escrow_authority", swap.key().as_ref()],
+        bump,
+        space = 8 + 1 // Discriminator + bump
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    // Only required for CFISH top-ups
+    #[account(address = marketplace_config.cfish_mint @ CustomError::UnsupportedPaymentMint)]
+    pub payment_mint: Option<Account<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = payment_mint,
+        token::authority = initiator
+    )]
+    pub initiator_payment_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        payer = initiator,
+        token::mint = payment_mint,
+        token::authority = escrow_authority,
+        seeds = [b"swap_vault", swap.key().as_ref()],
+        bump
+    )]
+    pub swap_vault: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for accept_swap instruction
+#[derive(Accounts)]
+pub struct AcceptSwap<'info> {
+    #[account(mut)]
+    pub counterparty: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match swap.initiator, enforced by the has_one constraint on the swap
+    pub initiator: AccountInfo<'info>,
+    #[account(mut,
+        has_one = initiator,
+        has_one = counterparty @ CustomError::NotSwapCounterparty,
+        has_one = escrow_authority,
+        seeds = [b"swap", initiator.key().as_ref(), swap.swap_id.to_le_bytes().as_ref()],
+        bump = swap.bump,
+        close = initiator
+    )]
+    pub swap: Account<'info, SwapProposal>,
+    #[account(mut,
+        seeds = [b"escrow_authority", swap.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = initiator
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    // Only required for CFISH top-ups
+    #[account(
+        mut,
+        seeds = [b"swap_vault", swap.key().as_ref()],
+        bump
+    )]
+    pub swap_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = marketplace_config.cfish_mint,
+        token::authority = initiator
+    )]
+    pub initiator_payment_token_account: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = marketplace_config.cfish_mint,
+        token::authority = counterparty
+    )]
+    pub counterparty_payment_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for reject_swap instruction
+#[derive(Accounts)]
+pub struct RejectSwap<'info> {
+    pub counterparty: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match swap.initiator, enforced by the has_one constraint on the swap
+    pub initiator: AccountInfo<'info>,
+    #[account(mut,
+        has_one = initiator,
+        has_one = counterparty @ CustomError::NotSwapCounterparty,
+        has_one = escrow_authority,
+        seeds = [b"swap", initiator.key().as_ref(), swap.swap_id.to_le_bytes().as_ref()],
+        bump = swap.bump,
+        close = initiator
+    )]
+    pub swap: Account<'info, SwapProposal>,
+    #[account(mut,
+        seeds = [b"escrow_authority", swap.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = initiator
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    // Only required for CFISH top-ups
+    #[account(
+        mut,
+        seeds = [b"swap_vault", swap.key().as_ref()],
+        bump
+    )]
+    pub swap_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::authority = initiator
+    )]
+    pub initiator_payment_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Account structures for cancel_swap instruction
+#[derive(Accounts)]
+pub struct CancelSwap<'info> {
+    #[account(mut)]
+    pub initiator: Signer<'info>,
+    #[account(mut,
+        has_one = initiator,
+        has_one = escrow_authority,
+        seeds = [b"swap", initiator.key().as_ref(), swap.swap_id.to_le_bytes().as_ref()],
+        bump = swap.bump,
+        close = initiator
+    )]
+    pub swap: Account<'info, SwapProposal>,
+    #[account(mut,
+        seeds = [b"escrow_authority", swap.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = initiator
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    // Only required for CFISH top-ups
+    #[account(
+        mut,
+        seeds = [b"swap_vault", swap.key().as_ref()],
+        bump
+    )]
+    pub swap_vault: Option<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::authority = initiator
+    )]
+    pub initiator_payment_token_account: Option<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Account structures for post_buy_intent instruction
+#[derive(Accounts)]
+#[instruction(intent_id: u64)]
+pub struct PostBuyIntent<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + 32 + 8 + 1 + 32 + 1 + 32 + 8 + 8 + 8 + 1, // Discriminator + buyer + intent_id + nft_mint + collection + max_price + expires_at + bounty + bump
+        seeds = [b"buy_intent", buyer.key().as_ref(), intent_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub buy_intent: Account<'info, BuyIntent>,
+    pub system_program: Program<'info, System>,
+}
+
+// Account structures for cancel_buy_intent instruction
+#[derive(Accounts)]
+pub struct CancelBuyIntent<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut,
+        has_one = buyer,
+        seeds = [b"buy_intent", buyer.key().as_ref(), buy_intent.intent_id.to_le_bytes().as_ref()],
+        bump = buy_intent.bump,
+        close = buyer
+    )]
+    pub buy_intent: Account<'info, BuyIntent>,
+}
+
+// Account structures for post_sell_intent instruction
+#[derive(Accounts)]
+pub struct PostSellIntent<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub nft_mint: Account<'info, Mint>,
+    // Required so the sale can pay royalties; only NFTs minted by this program can be sold
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(
+        mut,
+        associated_token::mint = nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 32 + 32 + 1, // Discriminator + seller + nft_mint + min_price + expires_at + bounty + escrow_nft_token_account + escrow_authority + bump
+        seeds = [b"sell_intent", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub sell_intent: Account<'info, SellIntent>,
+    #[account(
+        init,
+        payer = seller,
+        seeds = [b"escrow_authority", sell_intent.key().as_ref()],
+        bump,
+        space = 8 + 1 // Discriminator + bump
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    // Seeded by the intent rather than an associated token account, which anyone could create first
+    #[account(
+        init,
+        payer = seller,
+        token::mint = nft_mint,
+        token::authority = escrow_authority,
+        seeds = [b"escrow_intent", sell_intent.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+// Account structures for cancel_sell_intent instruction
+#[derive(Accounts)]
+pub struct CancelSellIntent<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(mut,
+        has_one = seller,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        seeds = [b"sell_intent", sell_intent.nft_mint.as_ref()],
+        bump = sell_intent.bump,
+        close = seller
+    )]
+    pub sell_intent: Account<'info, SellIntent>,
+    #[account(mut,
+        seeds = [b"escrow_authority", sell_intent.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    #[account(mut,
+        seeds = [b"escrow_intent", sell_intent.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = sell_intent.nft_mint,
+        associated_token::authority = seller
+    )]
+    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Account structures for match_intents instruction
+#[derive(Accounts)]
+pub struct MatchIntents<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Must match buy_intent.buyer, enforced by the has_one constraint on the intent
+    pub buyer: AccountInfo<'info>,
+    #[account(mut,
+        has_one = buyer,
+        seeds = [b"buy_intent", buyer.key().as_ref(), buy_intent.intent_id.to_le_bytes().as_ref()],
+        bump = buy_intent.bump,
+        close = buyer
+    )]
+    pub buy_intent: Account<'info, BuyIntent>,
+    #[account(mut)]
+    /// CHECK: Must match sell_intent.seller, enforced by the has_one constraint on the intent
+    pub seller: AccountInfo<'info>,
+    #[account(mut,
+        has_one = seller,
+        has_one = nft_mint,
+        has_one = escrow_nft_token_account,
+        has_one = escrow_authority,
+        seeds = [b"sell_intent", nft_mint.key().as_ref()],
+        bump = sell_intent.bump,
+        close = seller
+    )]
+    pub sell_intent: Account<'info, SellIntent>,
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        seeds = [b"nft_metadata", nft_mint.key().as_ref()],
+        bump
+    )]
+    pub nft_metadata: Account<'info, NftMetadata>,
+    #[account(mut,
+        seeds = [b"escrow_authority", sell_intent.key().as_ref()],
+        bump = escrow_authority.bump,
+        close = seller
+    )]
+    pub escrow_authority: Account<'info, EscrowAuthority>,
+    #[account(mut,
+        seeds = [b"escrow_intent", sell_intent.key().as_ref()],
+        bump
+    )]
+    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        associated_token::mint = nft_mint,
+        associated_token::authority = buyer
+    )]
+    pub buyer_nft_token_account: Account<'info, TokenAccount>,
+    #[account(
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
+    )]
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    /// CHECK: Receives the platform fee, validated against the marketplace config
+    #[account(mut, address = marketplace_config.treasury)]
+    pub treasury: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
 }
 
-// Account structures for mint_nft instruction
+// Account structures for sweep_expired_intents instruction
 #[derive(Accounts)]
-pub struct MintNft<
-    'info
-> {
-    #[account(mut)]
-    pub mint: Account<'info, Mint>,
+pub struct SweepExpiredIntents<'info> {
     #[account(mut)]
-    pub mint_authority: Signer<'info>,
+    pub cranker: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+// Account structures for create_referral_code instruction
+#[derive(Accounts)]
+#[instruction(code: String)]
+pub struct CreateReferralCode<'info> {
     #[account(mut)]
-    pub payer: Signer<'info>,
+    pub referrer: Signer<'info>,
     #[account(
         init,
-        payer = payer,
-        space = 8 + 32 + 32 + 4 + 100 + 4 + 10 + 4 + 200, // Discriminator + mint + creator + name + symbol + uri
-        seeds = [b"nft_metadata", mint.key().as_ref()],
+        payer = referrer,
+        space = 8 + 32 + 4 + MAX_REFERRAL_CODE_LEN + 8 + 1, // Discriminator + referrer + code + claimable + bump
+        seeds = [b"referral_code", code.as_bytes()],
         bump
     )]
-    pub nft_metadata: Account<'info, NftMetadata>,
+    pub referral_code: Account<'info, ReferralCode>,
     pub system_program: Program<'info, System>,
 }
 
-// Account structures for list_nft instruction
+// Account structures for register_referral instruction
 #[derive(Accounts)]
-#[instruction(price: u64)]
-pub struct ListNft<'info> {
-    #[account(mut)]
-    pub seller: Signer<'info>,
+#[instruction(code: String)]
+pub struct RegisterReferral<'info> {
     #[account(mut)]
-    pub nft_mint: Account<'info, Mint>,
-    #[account(
-        mut,
-        associated_token::mint = nft_mint,
-        associated_token::authority = seller
-    )]
-    pub seller_nft_token_account: Account<'info, TokenAccount>,
+    pub user: Signer<'info>,
     #[account(
-        init,
-        payer = seller,
-        token::mint = nft_mint,
-        token::authority = escrow_authority,
-        seeds = [b"escrow", nft_mint.key().as_ref()],
-        bump
+        seeds = [b"referral_code", code.as_bytes()],
+        bump = referral_code.bump
     )]
-    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    pub referral_code: Account<'info, ReferralCode>,
     #[account(
         init,
-        payer = seller,
-        space = 8 + 32 + 32 + 8 + 32 + 32 + 1, // Discriminator + seller + nft_mint + price + escrow_nft_token_account + escrow_authority + is_sold
-        seeds = [b"listing", nft_mint.key().as_ref()],
+        payer = user,
+        space = 8 + 32 + 32 + 1, // Discriminator + user + referral_code + bump
+        seeds = [b"referral", user.key().as_ref()],
         bump
     )]
-    pub listing: Account<'info, Listing>,
-    pub token_program: Program<'info, Token>,
+    pub referral: Account<'info, Referral>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
-    /// CHECK: This is the PDA authority for the escrow account
-    #[account(
-        init,
-        payer = seller,
-        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
-        bump,
-        space = 8 + 1 // Discriminator + bump
-    )]
-    pub escrow_authority: Account<'info, EscrowAuthority>,
 }
 
-// Account structures for buy_nft instruction
+// Account structures for claim_referral_rewards instruction
 #[derive(Accounts)]
-pub struct BuyNft<'info> {
-    #[account(mut)]
-    pub buyer: Signer<'info>,
-    #[account(mut)]
-    /// CHECK: The seller's account is validated by the listing account
-    pub seller: AccountInfo<'info>,
-    #[account(mut,
-        constraint = listing.nft_mint == nft_mint.key(),
-        constraint = listing.is_sold == false,
-        has_one = nft_mint,
-        has_one = escrow_nft_token_account,
-        has_one = escrow_authority
+pub struct ClaimReferralRewards<'info> {
+    pub referrer: Signer<'info>,
+    #[account(
+        mut,
+        has_one = referrer,
+        seeds = [b"referral_code", referral_code.code.as_bytes()],
+        bump = referral_code.bump
     )]
-    pub listing: Account<'info, Listing>,
-    #[account(mut)]
-    pub nft_mint: Account<'info, Mint>,
+    pub referral_code: Account<'info, ReferralCode>,
     #[account(
-        init_if_needed,
-        payer = buyer,
-        associated_token::mint = nft_mint,
-        associated_token::authority = buyer
+        seeds = [b"marketplace_config"],
+        bump = marketplace_config.bump
     )]
-    pub buyer_nft_token_account: Account<'info, TokenAccount>,
-    #[account(mut,
-        token::mint = nft_mint,
-        token::authority = escrow_authority,
-        seeds = [b"escrow", nft_mint.key().as_ref()],
+    pub marketplace_config: Account<'info, MarketplaceConfig>,
+    #[account(
+        mut,
+        seeds = [b"referral_vault"],
         bump
     )]
-    pub escrow_nft_token_account: Account<'info, TokenAccount>,
+    pub referral_vault: Account<'info, TokenAccount>,
     #[account(
-        seeds = [b"escrow_authority", nft_mint.key().as_ref()],
-        bump
+        mut,
+        token::mint = marketplace_config.cfish_mint,
+        token::authority = referrer
     )]
-    pub escrow_authority: Account<'info, EscrowAuthority>,
+    pub referrer_cfish_token_account: Account<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 // Account structures for stake instruction
@@ -521,7 +3971,6 @@ pub struct Vote<'info> {
     pub voter: Signer<'info>,
     #[account(mut,
         constraint = proposal.end_time > Clock::get()?.unix_timestamp,
-        constraint = proposal.proposer == proposal.proposer,
     )]
     pub proposal: Account<'info, Proposal>,
     #[account(mut,
@@ -578,8 +4027,483 @@ pub struct ReleaseVestedReward<'info> {
     pub system_program: Program<'info, System>,
 }
 
+// Helpers
+
+// Creator shares must add up to 100 and follow the Metaplex limits
+fn validate_creators(creators: &[CreatorShare], seller_fee_basis_points: u16) -> Result<()> {
+    require!(creators.len() <= MAX_CREATORS, CustomError::TooManyCreators);
+    require!(seller_fee_basis_points <= 10_000 - MAX_PLATFORM_FEE_BASIS_POINTS, CustomError::InvalidRoyaltyBasisPoints);
+    let total_share: u16 = creators.iter().map(|creator| creator.share as u16).sum();
+    require!(creators.is_empty() || total_share == 100, CustomError::InvalidCreatorShares);
+    Ok(())
+}
+
+// Where the funds for a settlement are drawn from
+pub enum PaymentSource<'a, 'info> {
+    // SOL from a signing wallet
+    Wallet {
+        payer: AccountInfo<'info>,
+        system_program: AccountInfo<'info>,
+    },
+    // SOL held in a program-owned escrow account
+    Escrow {
+        account: AccountInfo<'info>,
+    },
+    // SPL tokens from a token account, signed by its authority
+    Token {
+        from: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        mint: Pubkey,
+        token_program: AccountInfo<'info>,
+        signer_seeds: &'a [&'a [&'a [u8]]],
+    },
+}
+
+impl<'info> PaymentSource<'_, 'info> {
+    fn pay(&self, to: &AccountInfo<'info>, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        match self {
+            PaymentSource::Wallet { payer, system_program: program } => {
+                let cpi_accounts = system_program::Transfer {
+                    from: payer.clone(),
+                    to: to.clone(),
+                };
+                let cpi_ctx = CpiContext::new(program.clone(), cpi_accounts);
+                system_program::transfer(cpi_ctx, amount)
+            }
+            PaymentSource::Escrow { account } => {
+                let mut from_lamports = account.try_borrow_mut_lamports()?;
+                **from_lamports = from_lamports.checked_sub(amount).unwrap();
+                let mut to_lamports = to.try_borrow_mut_lamports()?;
+                **to_lamports = to_lamports.checked_add(amount).unwrap();
+                Ok(())
+            }
+            PaymentSource::Token { from, authority, token_program, signer_seeds, .. } => {
+                let cpi_accounts = Transfer {
+                    from: from.clone(),
+                    to: to.clone(),
+                    authority: authority.clone(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+                transfer(cpi_ctx, amount)
+            }
+        }
+    }
+
+    // Whether a recipient account pays out to the given wallet
+    fn pays_to(&self, to: &AccountInfo<'info>, wallet: Pubkey) -> bool {
+        match self {
+            PaymentSource::Wallet { .. } | PaymentSource::Escrow { .. } => to.key() == wallet,
+            PaymentSource::Token { mint, .. } => {
+                let Ok(data) = to.try_borrow_data() else {
+                    return false;
+                };
+                to.owner == &Token::id()
+                    && TokenAccount::try_deserialize(&mut &data[..])
+                        .map(|token_account| token_account.owner == wallet && token_account.mint == *mint)
+                        .unwrap_or(false)
+            }
+        }
+    }
+}
+
+// Pays the platform fee, the creator royalties and the seller out of a sale price.
+// Creator accounts follow nft_metadata.creators order. Returns (fee, royalties).
+fn settle_sale<'info>(
+    source: &PaymentSource<'_, 'info>,
+    price: u64,
+    fee_basis_points: u16,
+    treasury: &AccountInfo<'info>,
+    nft_metadata: &NftMetadata,
+    creator_accounts: &[AccountInfo<'info>],
+    seller: &AccountInfo<'info>,
+) -> Result<(u64, u64)> {
+    let fee = platform_fee(price, fee_basis_points);
+    source.pay(treasury, fee)?;
+
+    let royalties = royalty_shares(price, nft_metadata);
+    require!(creator_accounts.len() >= royalties.len(), CustomError::CreatorMismatch);
+    let mut total_royalty: u64 = 0;
+    for ((creator, amount), creator_account) in nft_metadata.creators.iter().zip(royalties).zip(creator_accounts) {
+        require!(source.pays_to(creator_account, creator.address), CustomError::CreatorMismatch);
+        source.pay(creator_account, amount)?;
+        total_royalty = total_royalty.checked_add(amount).unwrap();
+    }
+
+    let seller_amount = price
+        .checked_sub(fee)
+        .and_then(|amount| amount.checked_sub(total_royalty))
+        .ok_or(CustomError::FeesExceedPrice)?;
+    source.pay(seller, seller_amount)?;
+    Ok((fee, total_royalty))
+}
+
+// Platform fee owed on a sale price
+fn platform_fee(price: u64, fee_basis_points: u16) -> u64 {
+    (price as u128 * fee_basis_points as u128 / 10_000) as u64
+}
+
+// Referrer's share of a SOL platform fee, converted to CFISH at the configured rate
+fn referral_commission(fee: u64, marketplace_config: &MarketplaceConfig) -> u64 {
+    let share = fee as u128 * marketplace_config.referral_share_bps as u128 / 10_000;
+    (share * marketplace_config.cfish_per_sol as u128 / LAMPORTS_PER_SOL as u128) as u64
+}
+
+// Credits the buyer's referrer with their share of a SOL platform fee. The buyer's referral
+// PDA is always passed, so a registered referral cannot be skipped by omitting accounts.
+fn accrue_referral(
+    referral: &AccountInfo,
+    referral_code: Option<&mut Account<ReferralCode>>,
+    fee: u64,
+    marketplace_config: &MarketplaceConfig,
+) -> Result<()> {
+    // The PDA of a buyer without a referral is not initialized
+    if referral.owner != &ID || referral.data_is_empty() {
+        return Ok(());
+    }
+    let referral = Referral::try_deserialize(&mut &referral.try_borrow_data()?[..])?;
+    let referral_code = referral_code.ok_or(CustomError::MissingReferralAccount)?;
+    require_keys_eq!(referral.referral_code, referral_code.key(), CustomError::ReferralCodeMismatch);
+
+    let commission = referral_commission(fee, marketplace_config);
+    referral_code.claimable = referral_code.claimable.checked_add(commission).unwrap();
+    msg!("Referral commission accrued: {} CFISH", commission);
+    Ok(())
+}
+
+// Splits the royalty on a sale price between the creators, in nft_metadata.creators order
+fn royalty_shares(price: u64, nft_metadata: &NftMetadata) -> Vec<u64> {
+    let royalty = price as u128 * nft_metadata.seller_fee_basis_points as u128 / 10_000;
+    nft_metadata
+        .creators
+        .iter()
+        .map(|creator| (royalty * creator.share as u128 / 100) as u64)
+        .collect()
+}
+
+// Platform fee plus royalties owed on a sale price
+fn sale_fees(price: u64, fee_basis_points: u16, nft_metadata: &NftMetadata) -> u64 {
+    royalty_shares(price, nft_metadata)
+        .into_iter()
+        .fold(platform_fee(price, fee_basis_points), |total, royalty| total.saturating_add(royalty))
+}
+
+// Whether a delegated listing's token account still holds the NFT and approves the escrow authority
+fn delegation_intact(token_account: &TokenAccount, escrow_authority: Pubkey) -> bool {
+    token_account.delegate == COption::Some(escrow_authority)
+        && token_account.delegated_amount >= 1
+        && token_account.amount >= 1
+}
+
+// Whether the accounts passed for a listing in a buy_many sweep belong to it
+fn sweep_accounts_match(
+    listing: &Listing,
+    nft_metadata: &NftMetadata,
+    escrow_nft_token_account: Pubkey,
+    escrow_authority: Pubkey,
+    seller: Pubkey,
+) -> bool {
+    listing.nft_mint == nft_metadata.mint
+        && listing.seller == seller
+        && listing.escrow_nft_token_account == escrow_nft_token_account
+        && listing.escrow_authority == escrow_authority
+}
+
+// A fixed-price listing purchase, shared by buy_nft and buy_many. Callers bind the accounts
+// to the listing and close the listing and escrow authority once it is settled.
+struct ListingPurchase<'a, 'info> {
+    listing: &'a Listing,
+    nft_metadata: &'a NftMetadata,
+    // The escrow PDA, or the seller's own token account for delegated listings
+    escrow_nft_token_account: &'a Account<'info, TokenAccount>,
+    escrow_authority: &'a Account<'info, EscrowAuthority>,
+    buyer: AccountInfo<'info>,
+    seller: AccountInfo<'info>,
+    buyer_nft_token_account: AccountInfo<'info>,
+    // Only required for listings priced in CFISH
+    buyer_payment_token_account: Option<AccountInfo<'info>>,
+    seller_payment_token_account: Option<AccountInfo<'info>>,
+    token_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+}
+
+impl<'info> ListingPurchase<'_, 'info> {
+    // Checks the listing can still be bought by this buyer for at most max_price
+    fn validate(&self, max_price: u64, current_time: i64) -> Result<()> {
+        let listing = self.listing;
+        require!(listing.price <= max_price, CustomError::PriceExceedsMax);
+        if let Some(expires_at) = listing.expires_at {
+            require!(current_time < expires_at, CustomError::ListingExpired);
+        }
+        if let Some(reserved_buyer) = listing.reserved_buyer {
+            require_keys_eq!(self.buyer.key(), reserved_buyer, CustomError::ReservedForAnotherBuyer);
+        }
+        if listing.delegated {
+            // The listing is void once the seller revokes the approval or moves the NFT
+            require!(
+                delegation_intact(self.escrow_nft_token_account, self.escrow_authority.key()),
+                CustomError::ListingDelegateRevoked
+            );
+        }
+        Ok(())
+    }
+
+    // Pays the platform fee, royalties and seller, then hands the NFT to the buyer.
+    // Returns (fee, royalties).
+    fn settle(
+        &self,
+        marketplace_config: &MarketplaceConfig,
+        treasury: &AccountInfo<'info>,
+        creator_accounts: &[AccountInfo<'info>],
+    ) -> Result<(u64, u64)> {
+        let listing = self.listing;
+
+        // Settle in SOL from the buyer's wallet, or in CFISH from the buyer's token account
+        let (source, seller, fee_basis_points) = match listing.payment_mint {
+            Some(payment_mint) => {
+                let buyer_payment_token_account = self.buyer_payment_token_account.clone()
+                    .ok_or(CustomError::MissingPaymentAccount)?;
+                let seller_payment_token_account = self.seller_payment_token_account.clone()
+                    .ok_or(CustomError::MissingPaymentAccount)?;
+                let source = PaymentSource::Token {
+                    from: buyer_payment_token_account,
+                    authority: self.buyer.clone(),
+                    mint: payment_mint,
+                    token_program: self.token_program.clone(),
+                    signer_seeds: &[],
+                };
+                require!(
+                    source.pays_to(&seller_payment_token_account, listing.seller),
+                    CustomError::SellerMismatch
+                );
+                // CFISH settlements are exempt from the platform fee
+                (source, seller_payment_token_account, 0)
+            }
+            None => {
+                let source = PaymentSource::Wallet {
+                    payer: self.buyer.clone(),
+                    system_program: self.system_program.clone(),
+                };
+                (source, self.seller.clone(), marketplace_config.fee_basis_points)
+            }
+        };
+
+        let (fee, total_royalty) = settle_sale(
+            &source,
+            listing.price,
+            fee_basis_points,
+            treasury,
+            self.nft_metadata,
+            creator_accounts,
+            &seller,
+        )?;
+
+        // Transfer NFT from escrow to buyer, or from the seller's wallet as delegate
+        let seeds = &[
+            b"escrow_authority",
+            listing.nft_mint.as_ref(),
+            &[self.escrow_authority.bump]
+        ];
+        let signer = &[&seeds[..]];
+
+        if listing.delegated {
+            let cpi_accounts = Transfer {
+                from: self.escrow_nft_token_account.to_account_info(),
+                to: self.buyer_nft_token_account.clone(),
+                authority: self.escrow_authority.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(self.token_program.clone(), cpi_accounts, signer);
+            transfer(cpi_ctx, 1)?; // Transfer 1 NFT
+        } else {
+            // Close the empty escrow token account so the NFT can be listed again
+            release_escrowed_nft(
+                &self.escrow_nft_token_account.to_account_info(),
+                &self.buyer_nft_token_account,
+                &self.escrow_authority.to_account_info(),
+                &self.seller,
+                &self.token_program,
+                signer,
+            )?;
+        }
+        Ok((fee, total_royalty))
+    }
+}
+
+// Creates an escrow associated token account for each NFT and moves the NFT into it.
+// Accounts come in [nft_mint, owner_nft_token_account, escrow_nft_token_account] triples.
+// Returns the escrowed mints in account order.
+fn escrow_nfts<'info>(
+    owner: &AccountInfo<'info>,
+    escrow_authority: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    associated_token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_accounts: &[AccountInfo<'info>],
+) -> Result<Vec<Pubkey>> {
+    let mut nft_mints = Vec::with_capacity(token_accounts.len() / 3);
+    for accounts in token_accounts.chunks(3) {
+        let (nft_mint, owner_nft_token_account, escrow_nft_token_account) = (&accounts[0], &accounts[1], &accounts[2]);
+
+        // Create the escrow token account. Its address is predictable, so anyone can create it
+        // first; accept an existing one rather than let that block the escrow
+        let cpi_accounts = Create {
+            payer: owner.clone(),
+            associated_token: escrow_nft_token_account.clone(),
+            authority: escrow_authority.clone(),
+            mint: nft_mint.clone(),
+            system_program: system_program.clone(),
+            token_program: token_program.clone(),
+        };
+        let cpi_ctx = CpiContext::new(associated_token_program.clone(), cpi_accounts);
+        create_idempotent(cpi_ctx)?;
+
+        // Transfer NFT from owner to escrow account
+        let cpi_accounts = Transfer {
+            from: owner_nft_token_account.clone(),
+            to: escrow_nft_token_account.clone(),
+            authority: owner.clone(),
+        };
+        let cpi_ctx = CpiContext::new(token_program.clone(), cpi_accounts);
+        transfer(cpi_ctx, 1)?; // Transfer 1 NFT
+
+        nft_mints.push(nft_mint.key());
+    }
+    Ok(nft_mints)
+}
+
+// Moves every escrowed NFT to the recipient's associated token accounts and closes the
+// escrow accounts. Accounts come in [escrow, recipient] pairs, in nft_mints order, and
+// account_mismatch is raised when they do not.
+#[allow(clippy::too_many_arguments)]
+fn release_escrowed_nfts<'info>(
+    nft_mints: &[Pubkey],
+    recipient: Pubkey,
+    escrow_authority: &AccountInfo<'info>,
+    rent_destination: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    token_accounts: &[AccountInfo<'info>],
+    signer: &[&[&[u8]]],
+    account_mismatch: CustomError,
+) -> Result<()> {
+    require_eq!(token_accounts.len(), nft_mints.len() * 2, account_mismatch);
+    for (nft_mint, accounts) in nft_mints.iter().zip(token_accounts.chunks(2)) {
+        let (escrow_nft_token_account, recipient_nft_token_account) = (&accounts[0], &accounts[1]);
+        require_keys_eq!(
+            escrow_nft_token_account.key(),
+            get_associated_token_address(&escrow_authority.key(), nft_mint),
+            account_mismatch
+        );
+        require_keys_eq!(
+            recipient_nft_token_account.key(),
+            get_associated_token_address(&recipient, nft_mint),
+            account_mismatch
+        );
+        release_escrowed_nft(
+            escrow_nft_token_account,
+            recipient_nft_token_account,
+            escrow_authority,
+            rent_destination,
+            token_program,
+            signer,
+        )?;
+    }
+    Ok(())
+}
+
+// Moves an escrowed NFT to the recipient's token account and closes the empty escrow
+// token account, refunding its rent to rent_destination
+fn release_escrowed_nft<'info>(
+    escrow_nft_token_account: &AccountInfo<'info>,
+    recipient_nft_token_account: &AccountInfo<'info>,
+    escrow_authority: &AccountInfo<'info>,
+    rent_destination: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    let cpi_accounts = Transfer {
+        from: escrow_nft_token_account.clone(),
+        to: recipient_nft_token_account.clone(),
+        authority: escrow_authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer);
+    transfer(cpi_ctx, 1)?; // Transfer 1 NFT
+
+    let cpi_accounts = CloseAccount {
+        account: escrow_nft_token_account.clone(),
+        destination: rent_destination.clone(),
+        authority: escrow_authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer);
+    close_account(cpi_ctx)
+}
+
+// Pays out the whole balance of a swap's CFISH top-up vault and closes it
+fn release_swap_vault<'info>(
+    swap_vault: &Account<'info, TokenAccount>,
+    to: &AccountInfo<'info>,
+    escrow_authority: &AccountInfo<'info>,
+    rent_destination: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    if swap_vault.amount > 0 {
+        let cpi_accounts = Transfer {
+            from: swap_vault.to_account_info(),
+            to: to.clone(),
+            authority: escrow_authority.clone(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer);
+        transfer(cpi_ctx, swap_vault.amount)?;
+    }
+
+    let cpi_accounts = CloseAccount {
+        account: swap_vault.to_account_info(),
+        destination: rent_destination.clone(),
+        authority: escrow_authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer);
+    close_account(cpi_ctx)
+}
+
+// Current price of a Dutch listing, decaying from start_price to floor_price
+fn dutch_price(dutch_listing: &DutchListing, current_time: i64) -> u64 {
+    if current_time <= dutch_listing.start_time {
+        return dutch_listing.start_price;
+    }
+    if current_time >= dutch_listing.end_time {
+        return dutch_listing.floor_price;
+    }
+    let mut elapsed = current_time - dutch_listing.start_time;
+    if dutch_listing.step_duration > 0 {
+        elapsed -= elapsed % dutch_listing.step_duration;
+    }
+    let duration = dutch_listing.end_time - dutch_listing.start_time;
+    let price_range = (dutch_listing.start_price - dutch_listing.floor_price) as u128;
+    let decay = price_range * elapsed as u128 / duration as u128;
+    dutch_listing.start_price - decay as u64
+}
+
 // Data structures
 
+pub const MAX_CREATORS: usize = 5;
+pub const PLATFORM_FEE_BASIS_POINTS: u16 = 200; // 2%
+pub const MAX_PLATFORM_FEE_BASIS_POINTS: u16 = 1_000; // 10%, royalties are capped at the rest of the price
+pub const MAX_BUNDLE_SIZE: usize = 8;
+pub const MAX_SWAP_SIZE: usize = 4;
+pub const MATCH_CRANK_FEE_BASIS_POINTS: u16 = 10; // 0.1%
+pub const MAX_REFERRAL_CODE_LEN: usize = 16;
+pub const REFERRAL_SHARE_BASIS_POINTS: u16 = 1_000; // 10%
+pub const MIN_REFERRAL_SHARE_BASIS_POINTS: u16 = 100; // 1%
+pub const MAX_REFERRAL_SHARE_BASIS_POINTS: u16 = 5_000; // 50%
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CreatorShare {
+    pub address: Pubkey,
+    pub share: u8, // Percentage of the royalty paid to this creator
+}
+
 #[account]
 pub struct NftMetadata {
     pub mint: Pubkey,
@@ -587,6 +4511,24 @@ pub struct NftMetadata {
     pub name: String,
     pub symbol: String,
     pub uri: String,
+    pub seller_fee_basis_points: u16, // Royalty paid to the creators on every sale
+    pub creators: Vec<CreatorShare>,
+    pub collection: Option<Pubkey>, // Mint of the collection NFT
+    pub collection_verified: bool, // Set by the collection creator through verify_collection
+}
+
+#[account]
+pub struct MasterEdition {
+    pub mint: Pubkey,
+    pub max_supply: u64,
+    pub supply: u64, // Number of editions printed so far
+    pub bump: u8,
+}
+
+#[account]
+pub struct Edition {
+    pub parent: Pubkey, // Mint of the master edition
+    pub edition: u64,
 }
 
 #[account]
@@ -594,9 +4536,134 @@ pub struct Listing {
     pub seller: Pubkey,
     pub nft_mint: Pubkey,
     pub price: u64,
+    pub payment_mint: Option<Pubkey>, // None when priced in SOL
+    pub escrow_nft_token_account: Pubkey,
+    pub escrow_authority: Pubkey,
+    pub expires_at: Option<i64>, // None for listings that never expire
+    pub reserved_buyer: Option<Pubkey>, // Set for private listings
+    pub delegated: bool, // NFT stays in the seller's wallet with the escrow authority as delegate
+}
+
+#[account]
+pub struct MarketplaceConfig {
+    pub authority: Pubkey,
+    pub fee_basis_points: u16,
+    pub treasury: Pubkey, // Receives platform fees on SOL settlements
+    pub cfish_mint: Pubkey, // Settlements in this mint are exempt from the platform fee
+    pub referral_share_bps: u16, // Share of the platform fee credited to referrers
+    pub cfish_per_sol: u64, // Rate referral commissions are converted to CFISH at
+    pub bump: u8,
+}
+
+#[account]
+pub struct Auction {
+    pub seller: Pubkey,
+    pub nft_mint: Pubkey,
+    pub escrow_nft_token_account: Pubkey,
+    pub escrow_authority: Pubkey,
+    pub reserve_price: u64, // Minimum opening bid, in lamports
+    pub min_bid_increment: u64,
+    pub highest_bid: u64,
+    pub highest_bidder: Option<Pubkey>,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub extension_window: i64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct DutchListing {
+    pub seller: Pubkey,
+    pub nft_mint: Pubkey,
+    pub escrow_nft_token_account: Pubkey,
+    pub escrow_authority: Pubkey,
+    pub start_price: u64, // In lamports
+    pub floor_price: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub step_duration: i64, // 0 for a linear decay
+    pub bump: u8,
+}
+
+#[account]
+pub struct Offer {
+    pub buyer: Pubkey,
+    pub nft_mint: Pubkey,
+    pub price: u64,
+    pub payment_mint: Option<Pubkey>, // None when offered in SOL
+    pub bump: u8,
+}
+
+#[account]
+pub struct CollectionOffer {
+    pub buyer: Pubkey,
+    pub collection: Pubkey,
+    pub price: u64, // Per item, in lamports
+    pub quantity: u64, // Items still wanted
+    pub bump: u8,
+}
+
+#[account]
+pub struct BundleListing {
+    pub seller: Pubkey,
+    pub bundle_id: u64,
+    pub price: u64, // In lamports, for the whole bundle
+    pub nft_mints: Vec<Pubkey>,
+    pub escrow_authority: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+pub struct SwapProposal {
+    pub initiator: Pubkey,
+    pub counterparty: Pubkey,
+    pub swap_id: u64,
+    pub offered_mints: Vec<Pubkey>, // Escrowed by the initiator
+    pub requested_mints: Vec<Pubkey>, // Held by the counterparty until acceptance
+    pub topup_mint: Option<Pubkey>, // None for SOL, otherwise the CFISH mint
+    pub initiator_topup: u64, // Escrowed in the swap account or the CFISH vault
+    pub counterparty_topup: u64,
+    pub escrow_authority: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+pub struct BuyIntent {
+    pub buyer: Pubkey,
+    pub intent_id: u64,
+    pub nft_mint: Option<Pubkey>, // Either a specific NFT
+    pub collection: Option<Pubkey>, // or any verified NFT of a collection
+    pub max_price: u64, // In lamports, escrowed in this account, crank fee included
+    pub expires_at: i64,
+    pub bounty: u64, // In lamports, paid to whoever sweeps the expired intent
+    pub bump: u8,
+}
+
+#[account]
+pub struct SellIntent {
+    pub seller: Pubkey,
+    pub nft_mint: Pubkey,
+    pub min_price: u64, // In lamports
+    pub expires_at: i64,
+    pub bounty: u64, // In lamports, paid to whoever sweeps the expired intent
     pub escrow_nft_token_account: Pubkey,
     pub escrow_authority: Pubkey,
-    pub is_sold: bool,
+    pub bump: u8,
+}
+
+#[account]
+pub struct ReferralCode {
+    pub referrer: Pubkey,
+    pub code: String,
+    pub claimable: u64, // Accrued CFISH commission
+    pub bump: u8,
+}
+
+#[account]
+pub struct Referral {
+    pub user: Pubkey,
+    pub referral_code: Pubkey,
+    pub bump: u8,
 }
 
 #[account]
@@ -667,6 +4734,128 @@ pub enum CustomError {
     VestingNotStarted,
     #[msg("No rewards to release")]
     NoRewardsToRelease,
+    #[msg("Too many creators")]
+    TooManyCreators,
+    #[msg("Creator shares must add up to 100")]
+    InvalidCreatorShares,
+    #[msg("Royalty basis points cannot exceed 10000 minus MAX_PLATFORM_FEE_BASIS_POINTS")]
+    InvalidRoyaltyBasisPoints,
+    #[msg("Max supply must be greater than zero")]
+    InvalidMaxSupply,
+    #[msg("All editions have already been printed")]
+    EditionSupplyExhausted,
+    #[msg("A printed edition cannot become a master edition")]
+    EditionIsPrint,
+    #[msg("Creator accounts do not match the NFT metadata")]
+    CreatorMismatch,
+    #[msg("Fee basis points cannot exceed MAX_PLATFORM_FEE_BASIS_POINTS")]
+    InvalidFeeBasisPoints,
+    #[msg("Platform fee and royalties exceed the sale price")]
+    FeesExceedPrice,
+    #[msg("Listings can only be priced in SOL or CFISH")]
+    UnsupportedPaymentMint,
+    #[msg("Payment token accounts are required for CFISH listings")]
+    MissingPaymentAccount,
+    #[msg("Listing price does not match the expected price")]
+    ListingPriceChanged,
+    #[msg("Seller account does not match the listing")]
+    SellerMismatch,
+    #[msg("Auction duration and extension window must be positive")]
+    InvalidAuctionDuration,
+    #[msg("Auction has already ended")]
+    AuctionEnded,
+    #[msg("Auction has not ended yet")]
+    AuctionNotEnded,
+    #[msg("Bid is below the reserve price or minimum increment")]
+    BidTooLow,
+    #[msg("Previous bidder account does not match the auction")]
+    PreviousBidderMismatch,
+    #[msg("Recipient must be the winning bidder, or the seller if there were no bids")]
+    AuctionRecipientMismatch,
+    #[msg("Auction already has bids")]
+    AuctionHasBids,
+    #[msg("Dutch auction needs start price >= floor price and a positive duration")]
+    InvalidDutchAuction,
+    #[msg("Current price exceeds the maximum price")]
+    PriceExceedsMax,
+    #[msg("NFT does not belong to this collection")]
+    CollectionMismatch,
+    #[msg("NFT collection membership is not verified")]
+    CollectionNotVerified,
+    #[msg("Offer quantity must be greater than zero")]
+    InvalidOfferQuantity,
+    #[msg("Listing expiry must be in the future")]
+    InvalidListingExpiry,
+    #[msg("Listing has expired")]
+    ListingExpired,
+    #[msg("Listing has not expired")]
+    ListingNotExpired,
+    #[msg("Bundle must contain between 1 and MAX_BUNDLE_SIZE NFTs")]
+    InvalidBundleSize,
+    #[msg("Bundle token account does not match the expected associated token account")]
+    BundleAccountMismatch,
+    #[msg("Listing is reserved for another buyer")]
+    ReservedForAnotherBuyer,
+    #[msg("Seller revoked the delegation or no longer holds the NFT")]
+    ListingDelegateRevoked,
+    #[msg("Listing payment mint does not match the expected payment mint")]
+    PaymentMintMismatch,
+    #[msg("Listing is sold, expired or no longer matches the accounts passed")]
+    ListingUnavailable,
+    #[msg("Sweep accounts must come in complete listing groups")]
+    InvalidSweepAccounts,
+    #[msg("Swap must offer and request between 1 and MAX_SWAP_SIZE NFTs")]
+    InvalidSwapSize,
+    #[msg("Swap counterparty cannot be the initiator")]
+    InvalidSwapCounterparty,
+    #[msg("Only the named counterparty can respond to this swap")]
+    NotSwapCounterparty,
+    #[msg("Buy intent must target either an NFT mint or a collection")]
+    InvalidIntentTarget,
+    #[msg("NFT does not match the buy intent target")]
+    IntentTargetMismatch,
+    #[msg("Sell intent price and crank fee exceed the buy intent maximum price")]
+    IntentPriceMismatch,
+    #[msg("Intent expiry must be in the future")]
+    InvalidIntentExpiry,
+    #[msg("Intent has expired")]
+    IntentExpired,
+    #[msg("Intent has not expired")]
+    IntentNotExpired,
+    #[msg("Intent accounts must come in complete buy or sell intent groups")]
+    InvalidIntentAccounts,
+    #[msg("Referral code must be 1 to MAX_REFERRAL_CODE_LEN alphanumeric characters")]
+    InvalidReferralCode,
+    #[msg("Users cannot refer themselves")]
+    SelfReferral,
+    #[msg("Referral share must be between 1% and 50% of the platform fee")]
+    InvalidReferralShare,
+    #[msg("Referral code account is required for buyers with a registered referral")]
+    MissingReferralAccount,
+    #[msg("Referral code does not match the buyer's registered referral")]
+    ReferralCodeMismatch,
+    #[msg("No referral rewards to claim")]
+    NothingToClaim,
+    #[msg("Only the program upgrade authority can initialize the marketplace config")]
+    NotUpgradeAuthority,
+    #[msg("Minimum bid increment must be greater than zero")]
+    InvalidBidIncrement,
+    #[msg("NFT metadata does not match the bundled NFT")]
+    BundleMetadataMismatch,
+    #[msg("Seller must hold the NFT to list it")]
+    NftNotHeld,
+    #[msg("Listing is not a delegated listing")]
+    ListingNotDelegated,
+    #[msg("Delegated listing is still backed by the seller's approval")]
+    ListingDelegationIntact,
+    #[msg("Swap token account does not match the expected associated token account")]
+    SwapAccountMismatch,
+    #[msg("Referral vault has no CFISH to pay rewards from")]
+    ReferralVaultEmpty,
+    #[msg("Auction can be settled, use settle_auction")]
+    AuctionSettleable,
+    #[msg("Platform fee and royalties would exceed any winning bid")]
+    AuctionCannotSettle,
 }
 
 
@@ -0,0 +1,289 @@
+// Integration tests for buy_nft, run against the program's native entrypoint with solana-program-test.
+// Listing state is written straight into the test bank so each test only exercises the purchase.
+
+use anchor_lang::solana_program::{program_option::COption, program_pack::Pack, system_program, sysvar};
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use anchor_spl::token::spl_token;
+use cfish_contract::{CustomError, EscrowAuthority, Listing, MarketplaceConfig, NftMetadata, Referral};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+
+const PRICE: u64 = 1_000_000_000;
+const FEE_BASIS_POINTS: u16 = 250;
+const STARTING_BALANCE: u64 = 10_000_000_000;
+
+// Anchor's entry ties the account slice and the account infos to one lifetime
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    cfish_contract::entry(program_id, accounts, data)
+}
+
+struct Market {
+    seller: Pubkey,
+    treasury: Pubkey,
+    nft_mint: Pubkey,
+}
+
+fn pda(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &cfish_contract::ID)
+}
+
+fn add_anchor_account<T: AccountSerialize>(program_test: &mut ProgramTest, address: Pubkey, value: &T) {
+    let mut data = Vec::new();
+    value.try_serialize(&mut data).unwrap();
+    program_test.add_account(
+        address,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: cfish_contract::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+fn add_packed_account<T: Pack>(program_test: &mut ProgramTest, address: Pubkey, value: T) {
+    let mut data = vec![0; T::LEN];
+    T::pack(value, &mut data).unwrap();
+    program_test.add_account(
+        address,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+fn add_wallet(program_test: &mut ProgramTest, address: Pubkey) {
+    program_test.add_account(
+        address,
+        Account {
+            lamports: STARTING_BALANCE,
+            data: vec![],
+            owner: system_program::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+// A SOL listing of a freshly minted NFT, escrowed the way list_nft leaves it
+fn listed_market(program_test: &mut ProgramTest, impostor: Pubkey) -> Market {
+    let market = Market {
+        seller: Pubkey::new_unique(),
+        treasury: Pubkey::new_unique(),
+        nft_mint: Pubkey::new_unique(),
+    };
+    add_wallet(program_test, market.seller);
+    add_wallet(program_test, market.treasury);
+    add_wallet(program_test, impostor);
+
+    let (config, config_bump) = pda(&[b"marketplace_config"]);
+    add_anchor_account(
+        program_test,
+        config,
+        &MarketplaceConfig {
+            authority: Pubkey::new_unique(),
+            fee_basis_points: FEE_BASIS_POINTS,
+            treasury: market.treasury,
+            cfish_mint: Pubkey::new_unique(),
+            referral_share_bps: 0,
+            cfish_per_sol: 0,
+            bump: config_bump,
+        },
+    );
+
+    add_packed_account(
+        program_test,
+        market.nft_mint,
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply: 1,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+    );
+    add_anchor_account(
+        program_test,
+        pda(&[b"nft_metadata", market.nft_mint.as_ref()]).0,
+        &NftMetadata {
+            mint: market.nft_mint,
+            creator: market.seller,
+            name: "Fish #1".to_string(),
+            symbol: "FISH".to_string(),
+            uri: "https://example.com/fish/1.json".to_string(),
+            seller_fee_basis_points: 0,
+            creators: vec![],
+            collection: None,
+            collection_verified: false,
+        },
+    );
+
+    let (escrow_authority, escrow_authority_bump) = pda(&[b"escrow_authority", market.nft_mint.as_ref()]);
+    add_anchor_account(program_test, escrow_authority, &EscrowAuthority { bump: escrow_authority_bump });
+    let escrow = pda(&[b"escrow", market.nft_mint.as_ref()]).0;
+    add_packed_account(
+        program_test,
+        escrow,
+        spl_token::state::Account {
+            mint: market.nft_mint,
+            owner: escrow_authority,
+            amount: 1,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        },
+    );
+    add_anchor_account(
+        program_test,
+        pda(&[b"listing", market.nft_mint.as_ref()]).0,
+        &Listing {
+            seller: market.seller,
+            nft_mint: market.nft_mint,
+            price: PRICE,
+            payment_mint: None,
+            escrow_nft_token_account: escrow,
+            escrow_authority,
+            expires_at: None,
+            reserved_buyer: None,
+            delegated: false,
+        },
+    );
+    market
+}
+
+fn buy_nft_instruction(market: &Market, buyer: Pubkey, seller: Pubkey) -> Instruction {
+    let nft_mint = market.nft_mint;
+    let accounts = cfish_contract::accounts::BuyNft {
+        buyer,
+        seller,
+        listing: pda(&[b"listing", nft_mint.as_ref()]).0,
+        nft_mint,
+        nft_metadata: pda(&[b"nft_metadata", nft_mint.as_ref()]).0,
+        marketplace_config: pda(&[b"marketplace_config"]).0,
+        treasury: market.treasury,
+        buyer_nft_token_account: get_associated_token_address(&buyer, &nft_mint),
+        escrow_nft_token_account: pda(&[b"escrow", nft_mint.as_ref()]).0,
+        escrow_authority: pda(&[b"escrow_authority", nft_mint.as_ref()]).0,
+        buyer_payment_token_account: None,
+        seller_payment_token_account: None,
+        referral: pda(&[b"referral", buyer.as_ref()]).0,
+        referral_code: None,
+        token_program: spl_token::ID,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+    };
+    Instruction {
+        program_id: cfish_contract::ID,
+        accounts: accounts.to_account_metas(None),
+        data: cfish_contract::instruction::BuyNft {
+            max_price: PRICE,
+            expected_payment_mint: None,
+        }
+        .data(),
+    }
+}
+
+async fn lamports(banks_client: &mut BanksClient, address: Pubkey) -> u64 {
+    banks_client.get_balance(address).await.unwrap()
+}
+
+#[tokio::test]
+async fn buy_nft_rejects_substituted_seller() {
+    let mut program_test = ProgramTest::new("cfish_contract", cfish_contract::ID, processor!(process_instruction));
+    let impostor = Keypair::new();
+    let market = listed_market(&mut program_test, impostor.pubkey());
+    let (mut banks_client, buyer, recent_blockhash) = program_test.start().await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[buy_nft_instruction(&market, buyer.pubkey(), impostor.pubkey())],
+        Some(&buyer.pubkey()),
+        &[&buyer],
+        recent_blockhash,
+    );
+    let error = banks_client.process_transaction(transaction).await.unwrap_err().unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(0, InstructionError::Custom(CustomError::SellerMismatch.into()))
+    );
+
+    // Nothing moved: the impostor was not paid and the listing is still open
+    assert_eq!(lamports(&mut banks_client, impostor.pubkey()).await, STARTING_BALANCE);
+    let listing = pda(&[b"listing", market.nft_mint.as_ref()]).0;
+    let data = banks_client.get_account(listing).await.unwrap().unwrap().data;
+    let listing = Listing::try_deserialize(&mut data.as_slice()).unwrap();
+    assert_eq!(listing.seller, market.seller);
+}
+
+#[tokio::test]
+async fn buy_nft_pays_listed_seller() {
+    let mut program_test = ProgramTest::new("cfish_contract", cfish_contract::ID, processor!(process_instruction));
+    let market = listed_market(&mut program_test, Pubkey::new_unique());
+    let (mut banks_client, buyer, recent_blockhash) = program_test.start().await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[buy_nft_instruction(&market, buyer.pubkey(), market.seller)],
+        Some(&buyer.pubkey()),
+        &[&buyer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let fee = PRICE * FEE_BASIS_POINTS as u64 / 10_000;
+    assert_eq!(lamports(&mut banks_client, market.treasury).await, STARTING_BALANCE + fee);
+    // The seller also gets back the rent of the closed listing, escrow and escrow authority
+    assert!(lamports(&mut banks_client, market.seller).await > STARTING_BALANCE + PRICE - fee);
+
+    let buyer_nft_token_account = get_associated_token_address(&buyer.pubkey(), &market.nft_mint);
+    let data = banks_client.get_account(buyer_nft_token_account).await.unwrap().unwrap().data;
+    assert_eq!(spl_token::state::Account::unpack(&data).unwrap().amount, 1);
+    let listing = pda(&[b"listing", market.nft_mint.as_ref()]).0;
+    assert!(banks_client.get_account(listing).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn buy_nft_requires_registered_referral_code() {
+    let mut program_test = ProgramTest::new("cfish_contract", cfish_contract::ID, processor!(process_instruction));
+    let market = listed_market(&mut program_test, Pubkey::new_unique());
+    let buyer = Keypair::new();
+    add_wallet(&mut program_test, buyer.pubkey());
+    let (referral, referral_bump) = pda(&[b"referral", buyer.pubkey().as_ref()]);
+    add_anchor_account(
+        &mut program_test,
+        referral,
+        &Referral {
+            user: buyer.pubkey(),
+            referral_code: Pubkey::new_unique(),
+            bump: referral_bump,
+        },
+    );
+    let (banks_client, _payer, recent_blockhash) = program_test.start().await;
+
+    // Leaving out the referral code must not skip the referrer's commission
+    let transaction = Transaction::new_signed_with_payer(
+        &[buy_nft_instruction(&market, buyer.pubkey(), market.seller)],
+        Some(&buyer.pubkey()),
+        &[&buyer],
+        recent_blockhash,
+    );
+    let error = banks_client.process_transaction(transaction).await.unwrap_err().unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(0, InstructionError::Custom(CustomError::MissingReferralAccount.into()))
+    );
+}